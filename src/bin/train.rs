@@ -0,0 +1,68 @@
+use otter::nnue::trainer::{Trainer, TrainingConfig};
+use std::{env, process};
+
+const USAGE: &str = "Usage: train [dataset] [options]
+
+Trains an evaluation network on lines of the form \"[FEN] | [score] | [result]\", with the score in centipawns and the
+result (1.0, 0.5 or 0.0) both from white's point of view.
+
+Options:
+    --epochs [n]        Passes over the training positions (default 10)
+    --batch [n]         Positions per optimizer step (default 16384)
+    --lr [rate]         Learning rate of Adam (default 0.001)
+    --wdl [weight]      Weight of the game result in the target, against the search score (default 0.3)
+    --validation [f]    Fraction of positions held out to measure validation loss (default 0.05)
+    --output [file]     File the network is saved to after every epoch (default otter.nnue)";
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let (path, config) = match parse_args(&args) {
+        Some(parsed) => parsed,
+        None => {
+            println!("{}", USAGE);
+            process::exit(1);
+        }
+    };
+
+    let mut trainer = match Trainer::load(path, config) {
+        Ok(trainer) => trainer,
+        Err(e) => {
+            println!("Unable to load training dataset: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let (training, validation) = trainer.sample_counts();
+    println!(
+        "Loaded {} training and {} validation positions",
+        training, validation
+    );
+
+    if let Err(e) = trainer.train() {
+        println!("Unable to save network: {}", e);
+        process::exit(1);
+    }
+}
+
+/// Reads the dataset path and training settings from the command line arguments
+fn parse_args(args: &[String]) -> Option<(&str, TrainingConfig)> {
+    let (path, options) = args.split_first()?;
+    let mut config = TrainingConfig::default();
+
+    for option in options.chunks(2) {
+        let value = option.get(1)?;
+
+        match option[0].as_str() {
+            "--epochs" => config.epochs = value.parse().ok()?,
+            "--batch" => config.batch_size = value.parse().ok()?,
+            "--lr" => config.learning_rate = value.parse().ok()?,
+            "--wdl" => config.wdl = value.parse().ok()?,
+            "--validation" => config.validation = value.parse().ok()?,
+            "--output" => config.output = value.clone(),
+            _ => return None,
+        }
+    }
+
+    Some((path, config))
+}
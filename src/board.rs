@@ -10,7 +10,9 @@ mod move_generator;
 mod perft;
 mod zobrist;
 
-pub use move_generator::{Magic, Move, MoveFlag};
+pub use move_generator::{
+    Magic, Move, MoveFlag, BISHOP_MAGICS, KING_MOVES, KNIGHT_MOVES, PAWN_ATTACKS, ROOK_MAGICS,
+};
 pub use perft::perft_divide;
 pub use zobrist::ZobristHash;
 
@@ -27,6 +29,8 @@ struct GameState {
     en_passant_square: Option<Square>,
     halfmove: u32, // halfmove counter, incremented after each color's move
     fullmove: u32, // fullmove counter, only incremented after black's move
+    pawn_hash: ZobristHash, // zobrist hash of only the pawns, which is restored along with the rest of the state
+    material_hash: ZobristHash, // zobrist hash of the amount of each piece, regardless of where they are
 }
 
 /// Overall representation of a chess game
@@ -43,6 +47,9 @@ pub struct Board {
 
     // stack containing previous hashes used for detection of threefold repetition
     position_history: Vec<ZobristHash>,
+
+    // stack containing the game state from before each null move, which are undone separately from regular moves
+    null_move_history: Vec<GameState>,
 }
 
 impl Board {
@@ -50,17 +57,21 @@ impl Board {
     ///
     /// The FEN string is validated, if invalid the board is set to the start state of the chess game
     pub fn new(fen: &str) -> Board {
-        // check if the given string is valid
-        let fen_parts = if check_valid_fen(fen) {
-            fen
-        } else {
+        Board::try_new(fen).unwrap_or_else(|| {
             // if not, just use the default fen string
             println!("Invalid FEN! Reverting to starting position.");
-            DEFAULT_FEN
+            Board::new(DEFAULT_FEN)
+        })
+    }
+
+    /// Generates a new `Board` from a given FEN string, or returns `None` if the FEN string or its position is invalid
+    pub fn try_new(fen: &str) -> Option<Board> {
+        // check if the given string is valid
+        if !check_valid_fen(fen) {
+            return None;
         }
-        .split(" ")
-        .map(|s| s.to_string())
-        .collect::<Vec<_>>();
+
+        let fen_parts = fen.split(" ").map(|s| s.to_string()).collect::<Vec<_>>();
 
         // build the bitboards for the struct
         let mut pieces = [Bitboard::EMPTY; NUM_PIECES];
@@ -93,10 +104,10 @@ impl Board {
             }
         }
 
-        let mut b = Board {
+        let b = Board::from_parts(
             pieces,
             colors,
-            game_state: GameState {
+            GameState {
                 current_turn: if fen_parts[1] == "w" {
                     Color::White
                 } else {
@@ -106,21 +117,13 @@ impl Board {
                 en_passant_square: ALGEBRAIC_NOTATION.iter().position(|&s| s == fen_parts[3]),
                 halfmove: fen_parts[4].parse().unwrap(),
                 fullmove: fen_parts[5].parse().unwrap(),
+                pawn_hash: 0,
+                material_hash: 0,
             },
-            piece_list: [None; BOARD_SIZE],
-            move_history: Vec::new(),
-            position_history: Vec::new(),
-        };
-
-        b.piece_list = b.build_piece_list();
+        );
 
         // other systems expect board to be in a valid state, so check if it is valid
-        if !b.is_legal_position() {
-            println!("Invalid FEN! Reverting to starting position.");
-            b = Board::default();
-        }
-
-        b
+        b.is_legal_position().then_some(b)
     }
 
     /// Returns a FEN string representing the current board position
@@ -193,6 +196,69 @@ impl Board {
         fen
     }
 
+    /// Returns the same position with the colors swapped, flipping the ranks so that each side keeps moving the same way
+    ///
+    /// Castling rights and the en passant square are flipped along with the pieces, so the side to move has the same
+    /// moves available and a symmetric evaluation should give the same score. Move history isn't kept.
+    pub fn mirrored(&self) -> Board {
+        let game_state = GameState {
+            current_turn: self.active_color().opposite(),
+            castle_rights: self.game_state.castle_rights.swap_colors(),
+            en_passant_square: self.game_state.en_passant_square.map(|square| square ^ 56),
+            ..self.game_state
+        };
+
+        Board::from_parts(
+            self.pieces.map(Bitboard::flip_ranks),
+            [self.colors[Color::Black].flip_ranks(), self.colors[Color::White].flip_ranks()],
+            game_state,
+        )
+    }
+
+    /// Returns the same position with the files flipped, so that pieces on the a-file move over to the h-file
+    ///
+    /// Colors and the side to move stay the same. Castling is not symmetric between both sides of the board, so no
+    /// board is given if either side can still castle. Move history isn't kept.
+    pub fn mirrored_files(&self) -> Option<Board> {
+        if self.any_castle_rights() {
+            return None;
+        }
+
+        let game_state = GameState {
+            en_passant_square: self.game_state.en_passant_square.map(|square| square ^ 7),
+            ..self.game_state
+        };
+
+        Some(Board::from_parts(
+            self.pieces.map(Bitboard::flip_files),
+            self.colors.map(Bitboard::flip_files),
+            game_state,
+        ))
+    }
+
+    /// Builds a board without move history from its bitboards and game state, rebuilding everything derived from them
+    fn from_parts(
+        pieces: [Bitboard; NUM_PIECES],
+        colors: [Bitboard; NUM_COLORS],
+        game_state: GameState,
+    ) -> Board {
+        let mut b = Board {
+            pieces,
+            colors,
+            game_state,
+            piece_list: [None; BOARD_SIZE],
+            move_history: Vec::new(),
+            position_history: Vec::new(),
+            null_move_history: Vec::new(),
+        };
+
+        b.piece_list = b.build_piece_list();
+        b.game_state.pawn_hash = b.build_pawn_hash();
+        b.game_state.material_hash = b.build_material_hash();
+
+        b
+    }
+
     /// Makes the given move and updates game state accordingly
     ///
     /// Assumes `m` is a valid and legal move
@@ -301,6 +367,51 @@ impl Board {
             self.game_state.fullmove += 1;
         }
 
+        // only pawn moves and pawn captures change the pawn hash
+        if m.piece == Piece::Pawn {
+            self.game_state.pawn_hash ^= ZOBRIST.piece(m.from, Piece::Pawn, moving_color);
+
+            if !matches!(m.flag, Promotion(_) | CapturePromotion(_, _)) {
+                self.game_state.pawn_hash ^= ZOBRIST.piece(m.to, Piece::Pawn, moving_color);
+            }
+        }
+
+        match m.flag {
+            Capture(Piece::Pawn) | CapturePromotion(Piece::Pawn, _) => {
+                self.game_state.pawn_hash ^= ZOBRIST.piece(m.to, Piece::Pawn, moving_color.opposite());
+            }
+            EnPassantCapture(enemy_pawn_square) => {
+                self.game_state.pawn_hash ^=
+                    ZOBRIST.piece(enemy_pawn_square, Piece::Pawn, moving_color.opposite());
+            }
+            _ => (),
+        }
+
+        // only captures and promotions change the material on the board, counts are taken after the move is made
+        let material_count =
+            |piece: Piece, color: Color| (self.pieces[piece] & self.colors[color]).count_bits();
+
+        match m.flag {
+            Capture(piece) | CapturePromotion(piece, _) => {
+                let count = material_count(piece, moving_color.opposite());
+                self.game_state.material_hash ^= ZOBRIST.piece(count, piece, moving_color.opposite());
+            }
+            EnPassantCapture(_) => {
+                let count = material_count(Piece::Pawn, moving_color.opposite());
+                self.game_state.material_hash ^=
+                    ZOBRIST.piece(count, Piece::Pawn, moving_color.opposite());
+            }
+            _ => (),
+        }
+
+        if let Promotion(piece) | CapturePromotion(_, piece) = m.flag {
+            let pawns = material_count(Piece::Pawn, moving_color);
+            let promoted = material_count(piece, moving_color) - 1;
+
+            self.game_state.material_hash ^= ZOBRIST.piece(pawns, Piece::Pawn, moving_color);
+            self.game_state.material_hash ^= ZOBRIST.piece(promoted, piece, moving_color);
+        }
+
         // refresh the piece list
         self.piece_list = self.build_piece_list();
     }
@@ -388,23 +499,49 @@ impl Board {
         self.piece_list = self.build_piece_list();
     }
 
+    /// Passes the turn to the other side without moving any piece, as used by null move pruning in the search
+    ///
+    /// The en passant square is cleared, and the hash changes along with the side to move. Must not be made while in
+    /// check, and must be undone with `unmake_null_move` before any regular move made before it is unmade.
+    pub fn make_null_move(&mut self) {
+        self.null_move_history.push(self.game_state);
+
+        self.game_state.current_turn = self.game_state.current_turn.opposite();
+        self.game_state.en_passant_square = None;
+        self.game_state.halfmove += 1;
+    }
+
+    /// Un-makes the last null move, restoring the side to move and en passant square from before it
+    pub fn unmake_null_move(&mut self) {
+        if let Some(prev_state) = self.null_move_history.pop() {
+            self.game_state = prev_state;
+        }
+    }
+
     /// Generates all legal moves from this position
     pub fn generate_moves(&self) -> Vec<Move> {
-        MoveGenerator::generate_moves(&self)
+        MoveGenerator::generate_moves(self)
     }
 
     /// Generates all legal capture moves from this position
     // TODO - add capture-only generation to move generator, this filtering is too slow
     pub fn generate_captures(&self) -> Vec<Move> {
-        MoveGenerator::generate_moves(&self)
+        MoveGenerator::generate_moves(self)
             .into_iter()
             .filter(|mov| mov.is_capture())
             .collect()
     }
 
+    /// Finds the legal move matching the given long algebraic notation string (ex: "e2e4" or "e7e8q")
+    pub fn parse_move(&self, move_string: &str) -> Option<Move> {
+        self.generate_moves()
+            .into_iter()
+            .find(|mov| mov.to_string() == move_string)
+    }
+
     /// Returns whether or not the active color is in check in this position
     pub fn in_check(&self) -> bool {
-        MoveGenerator::in_check(&self)
+        MoveGenerator::in_check(self)
     }
 
     /// Checks for cases where a draw is possible and returns whether or not it is
@@ -432,6 +569,30 @@ impl Board {
         false
     }
 
+    /// Returns whether the game is drawn by the 50 move rule or threefold repetition, as an arbiter would rule it
+    ///
+    /// Unlike `is_drawable`, this doesn't treat positions close to a draw as drawn, so it can be used to end a game.
+    pub fn is_rule_draw(&self) -> bool {
+        // the current position isn't part of the history, so two earlier occurrences make it threefold
+        self.game_state.halfmove >= 100 || self.repetitions() >= 2
+    }
+
+    /// Returns whether the current position has already occurred earlier in the game
+    pub fn is_repetition(&self) -> bool {
+        self.repetitions() > 0
+    }
+
+    /// Amount of times the current position occurred before, not counting the current position itself
+    fn repetitions(&self) -> usize {
+        let current_hash = self.zobrist();
+        self.position_history.iter().filter(|hash| **hash == current_hash).count()
+    }
+
+    /// Amount of halfmoves since the last capture or pawn move, as used for the 50 move rule
+    pub fn halfmove_clock(&self) -> u32 {
+        self.game_state.halfmove
+    }
+
     /// Returns the piece type at the given square or `None` if no piece is at the square
     pub fn piece_at(&self, square: Square) -> Option<Piece> {
         self.piece_list[square]
@@ -484,6 +645,27 @@ impl Board {
             .get(self.game_state.current_turn, CastleSide::Queenside)
     }
 
+    /// Generates a bitboard of pieces matching the given type and color
+    pub fn piece_board(&self, piece: Piece, color: Color) -> Bitboard {
+        self.pieces[piece] & self.colors[color]
+    }
+
+    /// Generates a bitboard of all pieces of the given color
+    pub fn color_board(&self, color: Color) -> Bitboard {
+        self.colors[color]
+    }
+
+    /// Returns true if the given color can still castle to either side
+    pub fn castle_rights(&self, color: Color) -> bool {
+        self.game_state.castle_rights.get(color, CastleSide::Kingside)
+            || self.game_state.castle_rights.get(color, CastleSide::Queenside)
+    }
+
+    /// Returns true if either side still has any castling rights
+    pub fn any_castle_rights(&self) -> bool {
+        self.castle_rights(Color::White) || self.castle_rights(Color::Black)
+    }
+
     /// Generates a bitboard of pieces matching the given type that can move this turn
     pub fn active_piece_board(&self, piece: Piece) -> Bitboard {
         self.pieces[piece] & self.colors[self.game_state.current_turn]
@@ -508,6 +690,46 @@ impl Board {
         list
     }
 
+    /// Generates a zobrist hash value of only the pawns on the board
+    fn build_pawn_hash(&self) -> ZobristHash {
+        let mut hash = 0;
+
+        for color in [Color::White, Color::Black] {
+            for square in self.piece_board(Piece::Pawn, color) {
+                hash ^= ZOBRIST.piece(square, Piece::Pawn, color);
+            }
+        }
+
+        hash
+    }
+
+    /// Returns a zobrist hash of only the pawns on the board, which is kept up to date as moves are made
+    pub fn pawn_zobrist(&self) -> ZobristHash {
+        self.game_state.pawn_hash
+    }
+
+    /// Generates a zobrist hash value of the amount of each piece on the board
+    ///
+    /// The n-th piece of each type and color is hashed as if it stood on square n, so only the counts matter
+    fn build_material_hash(&self) -> ZobristHash {
+        let mut hash = 0;
+
+        for color in [Color::White, Color::Black] {
+            for piece in ALL_PIECES {
+                for count in 0..self.piece_board(piece, color).count_bits() {
+                    hash ^= ZOBRIST.piece(count, piece, color);
+                }
+            }
+        }
+
+        hash
+    }
+
+    /// Returns a zobrist hash of the material on the board, which is kept up to date as moves are made
+    pub fn material_zobrist(&self) -> ZobristHash {
+        self.game_state.material_hash
+    }
+
     /// Generates a zobrist hash value representing the current board state
     // TODO - incrementally update this hash instead of generating it fresh every time
     pub fn zobrist(&self) -> ZobristHash {
@@ -518,16 +740,13 @@ impl Board {
 
         // squares
         for (square, piece_option) in self.piece_list.iter().enumerate() {
-            match piece_option {
-                Some(piece) => {
-                    let color = match self.colors[Color::White].bit_at(square) {
-                        true => Color::White,
-                        false => Color::Black,
-                    };
-
-                    hash ^= ZOBRIST.piece(square, *piece, color);
-                }
-                None => (),
+            if let Some(piece) = piece_option {
+                let color = match self.colors[Color::White].bit_at(square) {
+                    true => Color::White,
+                    false => Color::Black,
+                };
+
+                hash ^= ZOBRIST.piece(square, *piece, color);
             }
         }
 
@@ -627,6 +846,7 @@ impl Clone for Board {
             piece_list: self.piece_list,
             move_history: Vec::new(),
             position_history: Vec::new(),
+            null_move_history: Vec::new(),
         }
     }
 }
@@ -662,9 +882,8 @@ impl Display for Board {
 
         // build the board string from the character array
         let mut output = String::new();
-        let mut index = 0;
 
-        for symbol in chars {
+        for (index, symbol) in chars.into_iter().enumerate() {
             if index % 8 == 0 {
                 output.push('\n');
                 output.push_str(&format!("{}   ", 8 - index / 8));
@@ -672,7 +891,6 @@ impl Display for Board {
 
             output.push(symbol);
             output.push(' ');
-            index += 1;
         }
 
         output.push_str("\n\n    a b c d e f g h\n");
@@ -722,6 +940,53 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_board_mirrored() {
+        let b = Board::new("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K1R1 w Qkq - 3 9");
+        assert_eq!(
+            b.mirrored().to_fen(),
+            "r3k1r1/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b KQq - 3 9"
+        );
+
+        // the en passant square is flipped along with the pawn that can be taken
+        let b = Board::new("4k3/8/8/2Pp4/8/8/8/4K3 w - d6 0 1");
+        assert_eq!(b.mirrored().to_fen(), "4k3/8/8/8/2pP4/8/8/4K3 b - d3 0 1");
+        assert_eq!(b.mirrored_files().unwrap().to_fen(), "3k4/8/8/4pP2/8/8/8/3K4 w - e6 0 1");
+
+        // flipping twice gives the original position, and the same moves are available in each flipped position
+        for fen in TEST_FENS {
+            let b = Board::new(fen);
+            assert_eq!(b.mirrored().mirrored().to_fen(), fen);
+            assert_eq!(b.mirrored().generate_moves().len(), b.generate_moves().len());
+            assert_eq!(b.mirrored().zobrist(), Board::new(&b.mirrored().to_fen()).zobrist());
+        }
+
+        // castling isn't symmetric between the files
+        assert!(Board::new(TEST_FENS[0]).mirrored_files().is_none());
+    }
+
+    #[test]
+    fn test_board_null_move() {
+        let fen = "rnbqkbnr/ppp1pppp/8/8/3pP3/5N2/PPPP1PPP/RNBQKB1R b KQkq e3 0 3";
+        let mut b = Board::new(fen);
+        let hash = b.zobrist();
+
+        // passing the turn clears the en passant square, which changes the hash along with the side to move
+        b.make_null_move();
+        assert_eq!(b.to_fen(), "rnbqkbnr/ppp1pppp/8/8/3pP3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 1 3");
+        assert_eq!(b.zobrist(), Board::new(&b.to_fen()).zobrist());
+        assert_ne!(b.zobrist(), hash);
+
+        // regular moves can be made and unmade on top of a null move
+        let m = b.parse_move("e4e5").unwrap();
+        b.make_move(m);
+        b.unmake_move();
+        b.unmake_null_move();
+
+        assert_eq!(b.to_fen(), fen);
+        assert_eq!(b.zobrist(), hash);
+    }
+
     #[test]
     fn test_board_zobrist() {
         let default_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
@@ -752,5 +1017,68 @@ mod tests {
         // check that same transpositions have the same hashes
         assert_eq!(b1.zobrist(), b2.zobrist());
     }
+
+    #[test]
+    fn test_board_pawn_zobrist() {
+        /// Walks every line to the given depth, checking the incrementally updated pawn hash against a fresh one
+        fn check_pawn_hash(board: &mut Board, depth: u8) {
+            assert_eq!(board.pawn_zobrist(), board.build_pawn_hash(), "{}", board.to_fen());
+
+            if depth == 0 {
+                return;
+            }
+
+            for m in board.generate_moves() {
+                board.make_move(m);
+                check_pawn_hash(board, depth - 1);
+                board.unmake_move();
+
+                assert_eq!(board.pawn_zobrist(), board.build_pawn_hash());
+            }
+        }
+
+        // positions with captures, en passant and promotions available
+        for fen in TEST_FENS {
+            check_pawn_hash(&mut Board::new(fen), 2);
+        }
+        check_pawn_hash(&mut Board::new("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1"), 3);
+
+        // pieces moving around don't change the pawn hash
+        let mut b = Board::default();
+        let before = b.pawn_zobrist();
+        b.make_move(b.parse_move("g1f3").unwrap());
+        assert_eq!(b.pawn_zobrist(), before);
+    }
+
+    #[test]
+    fn test_board_material_zobrist() {
+        /// Walks every line to the given depth, checking the incrementally updated material hash against a fresh one
+        fn check_material_hash(board: &mut Board, depth: u8) {
+            assert_eq!(board.material_zobrist(), board.build_material_hash(), "{}", board.to_fen());
+
+            if depth == 0 {
+                return;
+            }
+
+            for m in board.generate_moves() {
+                board.make_move(m);
+                check_material_hash(board, depth - 1);
+                board.unmake_move();
+
+                assert_eq!(board.material_zobrist(), board.build_material_hash());
+            }
+        }
+
+        for fen in TEST_FENS {
+            check_material_hash(&mut Board::new(fen), 2);
+        }
+
+        // the same material in different places shares a hash, while different material doesn't
+        let a = Board::new("4k3/8/8/8/8/8/8/R3K2N w - - 0 1");
+        let b = Board::new("4k3/8/2N5/8/8/3R4/8/4K3 b - - 0 1");
+        let c = Board::new("4k3/8/8/8/8/8/8/R3K2B w - - 0 1");
+        assert_eq!(a.material_zobrist(), b.material_zobrist());
+        assert_ne!(a.material_zobrist(), c.material_zobrist());
+    }
 }
 
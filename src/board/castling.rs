@@ -35,7 +35,7 @@ impl CastleRights {
     }
 
     /// Converts structure back to FEN segment
-    pub fn to_fen_segment(&self) -> String {
+    pub fn to_fen_segment(self) -> String {
         self.to_string()
     }
 
@@ -52,6 +52,16 @@ impl CastleRights {
         }
     }
 
+    /// Gives each color the castling rights of the other color
+    pub fn swap_colors(self) -> CastleRights {
+        CastleRights {
+            white_kingside: self.black_kingside,
+            white_queenside: self.black_queenside,
+            black_kingside: self.white_kingside,
+            black_queenside: self.white_queenside,
+        }
+    }
+
     /// Given a `Color` and `CastleSide`, sets castling rights
     ///
     /// Generally only used for internal castling management
@@ -91,11 +101,9 @@ impl CastleRights {
         let active_queenside = self.get(moving_color, Queenside);
 
         // if any king move is made for the active side, remove rights
-        if active_kingside || active_queenside {
-            if mov.piece == King {
-                self.set(moving_color, Kingside, false);
-                self.set(moving_color, Queenside, false);
-            }
+        if (active_kingside || active_queenside) && mov.piece == King {
+            self.set(moving_color, Kingside, false);
+            self.set(moving_color, Queenside, false);
         }
 
         // if any move for active side from initial rook position is made, remove that side's rights
@@ -8,14 +8,11 @@ mod magic;
 mod masks;
 mod moves;
 
-pub use magic::Magic;
+pub use direction::{KING_MOVES, KNIGHT_MOVES, PAWN_ATTACKS};
+pub use magic::{Magic, BISHOP_MAGICS, ROOK_MAGICS};
 pub use moves::{Move, MoveFlag};
 
-use direction::{
-    BISHOP_RAYS, KING_MOVES, KNIGHT_MOVES, PAWN_ATTACKS, PAWN_DOUBLE, PAWN_SINGLE, QUEEN_RAYS,
-    ROOK_RAYS,
-};
-use magic::{BISHOP_MAGICS, ROOK_MAGICS};
+use direction::{BISHOP_RAYS, PAWN_DOUBLE, PAWN_SINGLE, QUEEN_RAYS, ROOK_RAYS};
 use masks::{CastleMask, RankPositionMask};
 
 pub struct MoveGenerator;
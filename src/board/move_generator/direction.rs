@@ -92,14 +92,14 @@ fn generate_knight_moves() -> [Bitboard; BOARD_SIZE] {
         // .  4  .  6  .
 
         // generate moves by bitshifting in each L shape
-        *board |= knight_position_ab_file_masked >> Direction::NW + Direction::W;
-        *board |= knight_position_ab_file_masked >> Direction::SW + Direction::W;
-        *board |= knight_position_a_file_masked >> Direction::NW + Direction::N;
-        *board |= knight_position_a_file_masked >> Direction::SW + Direction::S;
-        *board |= knight_position_h_file_masked >> Direction::NE + Direction::N;
-        *board |= knight_position_h_file_masked >> Direction::SE + Direction::S;
-        *board |= knight_position_gh_file_masked >> Direction::NE + Direction::E;
-        *board |= knight_position_gh_file_masked >> Direction::SE + Direction::E;
+        *board |= knight_position_ab_file_masked >> (Direction::NW + Direction::W);
+        *board |= knight_position_ab_file_masked >> (Direction::SW + Direction::W);
+        *board |= knight_position_a_file_masked >> (Direction::NW + Direction::N);
+        *board |= knight_position_a_file_masked >> (Direction::SW + Direction::S);
+        *board |= knight_position_h_file_masked >> (Direction::NE + Direction::N);
+        *board |= knight_position_h_file_masked >> (Direction::SE + Direction::S);
+        *board |= knight_position_gh_file_masked >> (Direction::NE + Direction::E);
+        *board |= knight_position_gh_file_masked >> (Direction::SE + Direction::E);
     }
 
     boards
@@ -186,8 +186,8 @@ fn generate_pawn_double_moves() -> [[Bitboard; BOARD_SIZE]; NUM_COLORS] {
         // . (P) .      .  1  .
 
         // generate moves by shifting in each moving direction (per color)
-        *white_board |= (pawn_position & RankPositionMask::SECOND) >> Direction::N + Direction::N;
-        *black_board |= (pawn_position & RankPositionMask::SEVENTH) >> Direction::S + Direction::S;
+        *white_board |= (pawn_position & RankPositionMask::SECOND) >> (Direction::N + Direction::N);
+        *black_board |= (pawn_position & RankPositionMask::SEVENTH) >> (Direction::S + Direction::S);
     }
 
     // build array so we can index it by color
@@ -26,10 +26,15 @@ impl Move {
     pub fn is_capture(self) -> bool {
         use MoveFlag::*;
 
-        match self.flag {
-            Capture(_) | CapturePromotion(_, _) | EnPassantCapture(_) => true,
-            _ => false,
-        }
+        matches!(
+            self.flag,
+            Capture(_) | CapturePromotion(_, _) | EnPassantCapture(_)
+        )
+    }
+
+    /// Whether the move neither captures nor promotes, leaving the material on the board the same
+    pub fn is_quiet(self) -> bool {
+        !self.is_capture() && !matches!(self.flag, MoveFlag::Promotion(_))
     }
 }
 
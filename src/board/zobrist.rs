@@ -1,7 +1,6 @@
 use super::castling::{CastleSide, NUM_CASTLE_SIDES};
 use crate::core::{Color, Piece, Square, BOARD_SIZE, NUM_COLORS, NUM_PIECES};
 use lazy_static::lazy_static;
-use rand::Rng;
 
 lazy_static! {
     /// Static reference to zobrist values for board hashing
@@ -29,10 +28,29 @@ pub struct ZobristValues {
     en_passant: [ZobristHash; BOARD_SIZE + 1],
 }
 
+/// Fixed seed for zobrist value generation, keeping hashes identical between runs so they can be persisted to files
+const ZOBRIST_SEED: u64 = 0x6F_74_74_65_72_5F_7A_62;
+
+/// Minimal deterministic random number generator (splitmix64) used to fill the zobrist tables
+///
+/// Generator taken from https://prng.di.unimi.it/splitmix64.c
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn gen(&mut self) -> ZobristHash {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
 impl ZobristValues {
     fn new() -> ZobristValues {
-        // reference random number generator
-        let mut rng = rand::thread_rng();
+        // reference random number generator, seeded so that hashes are stable across restarts
+        let mut rng = SplitMix64(ZOBRIST_SEED);
 
         let mut z = ZobristValues {
             pieces: [[[0; BOARD_SIZE]; NUM_PIECES]; NUM_COLORS],
@@ -44,23 +62,23 @@ impl ZobristValues {
         for i in z.pieces.iter_mut() {
             for j in i.iter_mut() {
                 for k in j.iter_mut() {
-                    *k = rng.gen_range(ZobristHash::MIN..ZobristHash::MAX);
+                    *k = rng.gen();
                 }
             }
         }
 
         for i in z.castling.iter_mut() {
             for j in i.iter_mut() {
-                *j = rng.gen_range(ZobristHash::MIN..ZobristHash::MAX);
+                *j = rng.gen();
             }
         }
 
         for i in z.active.iter_mut() {
-            *i = rng.gen_range(ZobristHash::MIN..ZobristHash::MAX);
+            *i = rng.gen();
         }
 
         for i in z.en_passant.iter_mut() {
-            *i = rng.gen_range(ZobristHash::MIN..ZobristHash::MAX);
+            *i = rng.gen();
         }
 
         z
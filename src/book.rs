@@ -0,0 +1,155 @@
+use crate::board::{Board, Move, ZobristHash};
+use rand::Rng;
+use std::{collections::HashMap, fs};
+
+mod learning;
+
+pub use learning::{BookLearning, GameResult};
+
+/// Opening lines used when no book file is available, each line is a sequence of moves from the starting position
+const DEFAULT_LINES: [&str; 16] = [
+    // open games
+    "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5a4 g8f6 e1g1 f8e7",
+    "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5 c2c3 g8f6 d2d3 d7d6",
+    "e2e4 e7e5 g1f3 b8c6 d2d4 e5d4 f3d4 g8f6 d4c6 b7c6",
+    "e2e4 e7e5 g1f3 g8f6 f3e5 d7d6 e5f3 f6e4 d2d4 d6d5",
+    // semi-open games
+    "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 a7a6",
+    "e2e4 c7c5 g1f3 b8c6 d2d4 c5d4 f3d4 g8f6 b1c3 e7e5",
+    "e2e4 e7e6 d2d4 d7d5 b1c3 g8f6 c1g5 f8e7 e4e5 f6d7",
+    "e2e4 c7c6 d2d4 d7d5 b1c3 d5e4 c3e4 c8f5 e4g3 f5g6",
+    // closed games
+    "d2d4 d7d5 c2c4 e7e6 b1c3 g8f6 c1g5 f8e7 e2e3 e8g8",
+    "d2d4 d7d5 c2c4 c7c6 g1f3 g8f6 b1c3 d5c4 a2a4 c8f5",
+    "d2d4 g8f6 c2c4 e7e6 b1c3 f8b4 e2e3 e8g8 f1d3 d7d5",
+    "d2d4 g8f6 c2c4 g7g6 b1c3 f8g7 e2e4 d7d6 g1f3 e8g8",
+    "d2d4 g8f6 c2c4 e7e6 g1f3 b7b6 g2g3 c8a6 b2b3 f8b4",
+    // flank openings
+    "c2c4 e7e5 b1c3 g8f6 g1f3 b8c6 g2g3 d7d5 c4d5 f6d5",
+    "g1f3 d7d5 g2g3 g8f6 f1g2 e7e6 e1g1 f8e7 d2d3 e8g8",
+    "c2c4 g8f6 b1c3 e7e6 e2e4 d7d5 e4e5 d5d4 e5f6 d4c3",
+];
+
+/// A candidate move from a book position, along with how many book lines pass through it
+#[derive(Clone, Copy)]
+struct BookMove {
+    mov: Move,
+    count: u32,
+}
+
+/// Opening book mapping positions to the moves known to be playable from them
+pub struct Book {
+    positions: HashMap<ZobristHash, Vec<BookMove>>,
+}
+
+impl Book {
+    /// Loads a book from a file of move lines, falling back to the built-in lines if the file cannot be read
+    ///
+    /// Each line of the file is a space-separated list of moves from the starting position, lines starting with `#` are ignored
+    pub fn load(path: &str) -> Book {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::from_lines(contents.lines()),
+            Err(_) => Self::from_lines(DEFAULT_LINES.into_iter()),
+        }
+    }
+
+    /// Builds the book by playing out each line from the starting position
+    fn from_lines<'a>(lines: impl Iterator<Item = &'a str>) -> Book {
+        let mut positions: HashMap<ZobristHash, Vec<BookMove>> = HashMap::new();
+
+        for line in lines.map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut board = Board::default();
+
+            for move_string in line.split_whitespace() {
+                // stop following this line as soon as it is no longer legal
+                let mov = match board.parse_move(move_string) {
+                    Some(mov) => mov,
+                    None => break,
+                };
+
+                let moves = positions.entry(board.zobrist()).or_default();
+
+                // lines sharing a move from the same position increase the weight of that move
+                match moves.iter_mut().find(|book_move| book_move.mov == mov) {
+                    Some(book_move) => book_move.count += 1,
+                    None => moves.push(BookMove { mov, count: 1 }),
+                }
+
+                board.make_move(mov);
+            }
+        }
+
+        Book { positions }
+    }
+
+    /// Picks a book move for the given position if one exists
+    ///
+    /// Moves are chosen randomly, weighted by their frequency in the book and by how well they have performed in past games
+    pub fn probe(&self, board: &Board, learning: &BookLearning) -> Option<Move> {
+        let key = board.zobrist();
+        let moves = self.positions.get(&key)?;
+
+        let weights = moves
+            .iter()
+            .map(|book_move| book_move.count as f64 * learning.weight(key, book_move.mov))
+            .collect::<Vec<_>>();
+
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        // walk through the cumulative weights until the randomly chosen point is passed
+        let mut point = rand::thread_rng().gen_range(0.0..total);
+
+        for (book_move, weight) in moves.iter().zip(weights) {
+            if point < weight {
+                return Some(book_move.mov);
+            }
+
+            point -= weight;
+        }
+
+        moves.last().map(|book_move| book_move.mov)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_lines_are_legal() {
+        for line in DEFAULT_LINES {
+            let mut board = Board::default();
+
+            for move_string in line.split_whitespace() {
+                let mov = board.parse_move(move_string);
+                assert!(mov.is_some(), "{} is illegal in line {}", move_string, line);
+
+                board.make_move(mov.unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_book_probe() {
+        let book = Book::from_lines(["e2e4 e7e5", "d2d4 d7d5", "e2e4 c7c5"].into_iter());
+        let learning = BookLearning::default();
+
+        // every move chosen from the start position must come from the book
+        let board = Board::default();
+        for _ in 0..20 {
+            let mov = book.probe(&board, &learning).unwrap().to_string();
+            assert!(mov == "e2e4" || mov == "d2d4");
+        }
+
+        // positions outside of the book have no moves
+        let board = Board::new("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1");
+        assert!(book.probe(&board, &learning).is_none());
+    }
+}
@@ -0,0 +1,262 @@
+use crate::{
+    board::{Move, ZobristHash},
+    core::Color,
+    search::Score,
+};
+use std::{collections::HashMap, fs, io};
+
+/// Lowest factor a book move's weight can be scaled by, so that poor lines are rarely played rather than never
+const MIN_WEIGHT_FACTOR: f64 = 0.05;
+
+/// Outcome of a game from the engine's point of view
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameResult {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl GameResult {
+    /// Result of a finished game for the given side, where a winner of `None` means the game was drawn
+    pub fn for_side(winner: Option<Color>, side: Color) -> GameResult {
+        match winner {
+            Some(winner) if winner == side => GameResult::Win,
+            Some(_) => GameResult::Loss,
+            None => GameResult::Draw,
+        }
+    }
+}
+
+/// Learned statistics about a single book move from a single position
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+struct LearningEntry {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+    eval_total: i64, // sum of the evaluations when the engine left the book after this move
+    eval_count: u32,
+}
+
+impl LearningEntry {
+    /// Expected score of this move between 0 (always lost) and 1 (always won), 0.5 when nothing is known
+    fn performance(&self) -> f64 {
+        let games = (self.wins + self.draws + self.losses) as f64;
+
+        // results are smoothed with a single imaginary drawn game so that one result doesn't dominate
+        let results = (self.wins as f64 + 0.5 * self.draws as f64 + 1.0) / (games + 2.0);
+
+        if self.eval_count == 0 {
+            return results;
+        }
+
+        // average evaluation when leaving the book is mapped to an expected score with a logistic curve
+        let average_eval = self.eval_total as f64 / self.eval_count as f64;
+        let evals = 1.0 / (1.0 + f64::powf(10.0, -average_eval / 400.0));
+
+        (results + evals) / 2.0
+    }
+}
+
+/// Persistent record of how book moves have performed in previous games
+///
+/// Entries are keyed by the zobrist hash of the position and the move played from it, so the hash must be stable between runs
+#[derive(Default)]
+pub struct BookLearning {
+    path: Option<String>,
+    entries: HashMap<(ZobristHash, String), LearningEntry>,
+
+    // book moves played by the engine in the current game and the evaluation when the book was left
+    played: Vec<(ZobristHash, Move)>,
+    exit_eval: Option<Score>,
+}
+
+impl BookLearning {
+    /// Loads learning data from the given file, starting fresh if the file does not exist yet
+    ///
+    /// Each line of the file is in the form `[key] [move] [wins] [draws] [losses] [eval total] [eval count]`
+    pub fn load(path: &str) -> BookLearning {
+        let mut learning = BookLearning {
+            path: Some(path.to_string()),
+            ..Default::default()
+        };
+
+        if let Ok(contents) = fs::read_to_string(path) {
+            for line in contents.lines() {
+                match Self::parse_line(line) {
+                    Some((key, entry)) => {
+                        learning.entries.insert(key, entry);
+                    }
+                    None => println!("Skipping invalid book learning entry: {}", line),
+                }
+            }
+        }
+
+        learning
+    }
+
+    /// Parses a single line of the learning file
+    fn parse_line(line: &str) -> Option<((ZobristHash, String), LearningEntry)> {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+
+        if tokens.len() != 7 {
+            return None;
+        }
+
+        let key = ZobristHash::from_str_radix(tokens[0], 16).ok()?;
+
+        let entry = LearningEntry {
+            wins: tokens[2].parse().ok()?,
+            draws: tokens[3].parse().ok()?,
+            losses: tokens[4].parse().ok()?,
+            eval_total: tokens[5].parse().ok()?,
+            eval_count: tokens[6].parse().ok()?,
+        };
+
+        Some(((key, tokens[1].to_string()), entry))
+    }
+
+    /// Writes all learning data back to the file it was loaded from
+    fn save(&self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let mut contents = String::new();
+
+        for ((key, mov), entry) in self.entries.iter() {
+            contents.push_str(&format!(
+                "{:016x} {} {} {} {} {} {}\n",
+                key,
+                mov,
+                entry.wins,
+                entry.draws,
+                entry.losses,
+                entry.eval_total,
+                entry.eval_count
+            ));
+        }
+
+        fs::write(path, contents)
+    }
+
+    /// Factor to scale a book move's weight by, where 1 is neutral and lines that performed poorly approach 0
+    pub fn weight(&self, key: ZobristHash, mov: Move) -> f64 {
+        match self.entries.get(&(key, mov.to_string())) {
+            // square the relative performance so that bad lines are pushed down quickly
+            Some(entry) => f64::max(MIN_WEIGHT_FACTOR, (2.0 * entry.performance()).powi(2)),
+            None => 1.0,
+        }
+    }
+
+    /// Records that the engine played the given book move from the position with the given key
+    pub fn record_book_move(&mut self, key: ZobristHash, mov: Move) {
+        self.played.push((key, mov));
+    }
+
+    /// Records the engine's evaluation of a searched position, only the first one after leaving the book is kept
+    pub fn record_search(&mut self, score: Score) {
+        if !self.played.is_empty() && self.exit_eval.is_none() {
+            self.exit_eval = Some(score);
+        }
+    }
+
+    /// Applies the result of the current game to all book moves played during it and saves the learning file
+    pub fn finish_game(&mut self, result: GameResult) {
+        for (key, mov) in self.played.drain(..) {
+            let entry = self.entries.entry((key, mov.to_string())).or_default();
+
+            match result {
+                GameResult::Win => entry.wins += 1,
+                GameResult::Draw => entry.draws += 1,
+                GameResult::Loss => entry.losses += 1,
+            }
+
+            if let Some(eval) = self.exit_eval {
+                entry.eval_total += eval as i64;
+                entry.eval_count += 1;
+            }
+        }
+
+        self.exit_eval = None;
+
+        if let Err(e) = self.save() {
+            println!("Unable to save book learning file: {}", e);
+        }
+    }
+
+    /// Forgets the book moves of the current game without learning from them, for games whose result isn't known
+    pub fn abandon_game(&mut self) {
+        self.played.clear();
+        self.exit_eval = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+
+    #[test]
+    fn test_learning_weights() {
+        let board = Board::default();
+        let e2e4 = board.parse_move("e2e4").unwrap();
+        let d2d4 = board.parse_move("d2d4").unwrap();
+
+        let mut learning = BookLearning::default();
+
+        // unknown moves are neutral
+        assert_eq!(learning.weight(board.zobrist(), e2e4), 1.0);
+
+        // losing badly after leaving the book should down-weight the line
+        learning.record_book_move(board.zobrist(), e2e4);
+        learning.record_search(-250);
+        learning.record_search(100); // ignored, only first evaluation out of book counts
+        learning.finish_game(GameResult::Loss);
+
+        // winning should up-weight the line
+        learning.record_book_move(board.zobrist(), d2d4);
+        learning.record_search(150);
+        learning.finish_game(GameResult::Win);
+
+        assert!(learning.weight(board.zobrist(), e2e4) < 1.0);
+        assert!(learning.weight(board.zobrist(), d2d4) > 1.0);
+
+        // games without a known result leave the weights alone
+        let weights = learning.entries.clone();
+        learning.record_book_move(board.zobrist(), e2e4);
+        learning.abandon_game();
+        learning.finish_game(GameResult::Win);
+        assert_eq!(learning.entries, weights);
+
+        assert_eq!(GameResult::for_side(Some(Color::Black), Color::Black), GameResult::Win);
+        assert_eq!(GameResult::for_side(Some(Color::White), Color::Black), GameResult::Loss);
+        assert_eq!(GameResult::for_side(None, Color::White), GameResult::Draw);
+    }
+
+    #[test]
+    fn test_learning_file_round_trip() {
+        let id = std::process::id();
+        let path = std::env::temp_dir().join(format!("otter_learning_test_{}.learn", id));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+
+        let board = Board::default();
+        let e2e4 = board.parse_move("e2e4").unwrap();
+
+        let mut learning = BookLearning::load(path);
+        learning.record_book_move(board.zobrist(), e2e4);
+        learning.record_search(-400);
+        learning.finish_game(GameResult::Loss);
+
+        // a freshly loaded file should reproduce the same weights
+        let reloaded = BookLearning::load(path);
+        assert_eq!(reloaded.entries, learning.entries);
+        assert_eq!(
+            reloaded.weight(board.zobrist(), e2e4),
+            learning.weight(board.zobrist(), e2e4)
+        );
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -9,7 +9,7 @@ use std::{
 
 pub const BOARD_SIZE: usize = 64;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Default)]
 pub struct Bitboard(pub u64);
 
 impl Bitboard {
@@ -74,15 +74,17 @@ impl Bitboard {
 
     /// Counts the number of 1 bits in the given bitboard
     pub fn count_bits(self) -> usize {
-        let mut copy = self;
-        let mut count = 0;
+        self.0.count_ones() as usize
+    }
 
-        while !copy.is_empty() {
-            copy.pop_first_square();
-            count += 1;
-        }
+    /// Flips the board vertically, so that the first rank becomes the eighth and so on
+    pub fn flip_ranks(self) -> Bitboard {
+        Bitboard(self.0.swap_bytes())
+    }
 
-        count
+    /// Flips the board horizontally, so that the a-file becomes the h-file and so on
+    pub fn flip_files(self) -> Bitboard {
+        Bitboard(self.0.reverse_bits().swap_bytes())
     }
 
     /// Returns the next subset enumerated from the given set
@@ -251,13 +253,13 @@ impl Mul for Bitboard {
     type Output = Bitboard;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        Bitboard(self.0 * rhs.0)
+        Bitboard(self.0.wrapping_mul(rhs.0))
     }
 }
 impl Sub for Bitboard {
     type Output = Bitboard;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        Bitboard(self.0 - rhs.0)
+        Bitboard(self.0.wrapping_sub(rhs.0))
     }
 }
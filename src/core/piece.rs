@@ -1,6 +1,6 @@
 use crate::search::Score;
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum Piece {
     Pawn,
     Knight,
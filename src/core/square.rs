@@ -1,4 +1,4 @@
-use super::BOARD_SIZE;
+use super::{Color, BOARD_SIZE};
 
 // although there are only 64 squares, a square is commonly used to index an array so this helps reduce so many casts
 pub type Square = usize;
@@ -26,3 +26,38 @@ pub const ALGEBRAIC_NOTATION: [&str; BOARD_SIZE] = [
     "a2", "b2", "c2", "d2", "e2", "f2", "g2", "h2",
     "a1", "b1", "c1", "d1", "e1", "f1", "g1", "h1",
 ];
+
+/// Returns the file of a square, where 0 is the a-file and 7 is the h-file
+pub const fn file_of(square: Square) -> usize {
+    square % 8
+}
+
+/// Returns the rank of a square, where 0 is the first rank and 7 is the eighth rank
+pub const fn rank_of(square: Square) -> usize {
+    7 - square / 8
+}
+
+/// Returns the rank of a square from the given color's point of view, where 0 is that color's back rank
+pub const fn relative_rank(square: Square, color: Color) -> usize {
+    match color {
+        Color::White => rank_of(square),
+        Color::Black => 7 - rank_of(square),
+    }
+}
+
+/// Returns the number of king moves needed to travel between two squares
+pub const fn distance(a: Square, b: Square) -> usize {
+    let file_distance = file_of(a).abs_diff(file_of(b));
+    let rank_distance = rank_of(a).abs_diff(rank_of(b));
+
+    if file_distance > rank_distance {
+        file_distance
+    } else {
+        rank_distance
+    }
+}
+
+/// Returns true if the square is a light square (h1 is light, a1 is dark)
+pub const fn is_light_square(square: Square) -> bool {
+    (file_of(square) + rank_of(square)) % 2 == 1
+}
@@ -0,0 +1,269 @@
+use crate::{
+    board::Board,
+    core::Color,
+    nnue::{Network, NnueEvaluator},
+    search::{Evaluator, Score, Searcher},
+};
+use rand::{seq::SliceRandom, Rng};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::Instant,
+};
+
+mod format;
+
+pub use format::{GameOutcome, PackedPosition, PositionReader, PositionWriter};
+
+/// Transposition table size (in MB) of each thread's searcher
+const DATAGEN_TT_SIZE: usize = 16;
+
+/// Games still going after this many plies are counted as draws
+const MAX_PLIES: usize = 400;
+
+/// A game is adjudicated as won once the score stays beyond this value for `ADJUDICATION_PLIES` plies in a row,
+/// positions with scores this large are not recorded either
+const WIN_SCORE: Score = 2000;
+const ADJUDICATION_PLIES: usize = 4;
+
+/// Amount of finished games between progress reports
+const REPORT_INTERVAL: usize = 100;
+
+/// Settings for generating self-play data
+pub struct DatagenConfig {
+    pub games: usize,
+    pub nodes: u64,          // positions searched for each move
+    pub random_plies: usize, // random moves played at the start of each game, so that every game is different
+    pub output: String,
+}
+
+/// Plays self-play games across every available thread, writing quiet positions from them to the output file
+///
+/// Returns the amount of positions written. If a network is given, it is used to evaluate positions during the games.
+pub fn generate(config: &DatagenConfig, network: Option<Arc<Network>>) -> io::Result<usize> {
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let mut writer = PositionWriter::new(BufWriter::new(File::create(&config.output)?))?;
+
+    let started = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel::<Vec<PackedPosition>>();
+    let start = Instant::now();
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let (sender, started, network) = (sender.clone(), &started, network.clone());
+
+            scope.spawn(move || match network {
+                Some(network) => {
+                    let searcher =
+                        Searcher::with_evaluator(DATAGEN_TT_SIZE, NnueEvaluator::new(network));
+                    play_games(searcher, config, started, sender)
+                }
+                None => play_games(Searcher::new(DATAGEN_TT_SIZE), config, started, sender),
+            });
+        }
+
+        // only the worker threads should keep the channel open
+        drop(sender);
+
+        let (mut games, mut total) = (0, 0);
+
+        for positions in receiver {
+            for position in positions.iter() {
+                writer.write(position)?;
+            }
+
+            games += 1;
+            total += positions.len();
+
+            if games % REPORT_INTERVAL == 0 || games == config.games {
+                println!(
+                    "Played {} games, {} positions ({:.0} positions/s)",
+                    games,
+                    total,
+                    total as f32 / start.elapsed().as_secs_f32()
+                );
+            }
+        }
+
+        writer.flush()?;
+        Ok(total)
+    })
+}
+
+/// Keeps playing games with a single thread's searcher until enough games have been started, sending off the
+/// positions of each one
+fn play_games<E: Evaluator>(
+    mut searcher: Searcher<E>,
+    config: &DatagenConfig,
+    started: &AtomicUsize,
+    sender: mpsc::Sender<Vec<PackedPosition>>,
+) {
+    searcher.set_node_limit(Some(config.nodes));
+    *searcher.get_search_control().lock().unwrap() = true;
+
+    while started.fetch_add(1, Ordering::Relaxed) < config.games {
+        let positions = play_game(&mut searcher, config.random_plies);
+
+        // the receiver only goes away if writing failed, so there is no point in carrying on
+        if sender.send(positions).is_err() {
+            break;
+        }
+    }
+}
+
+/// Plays a single game from a random opening, returning its recorded positions labeled with the final outcome
+///
+/// Only quiet positions are recorded: the side to move is not in check and the best move found is not a capture.
+fn play_game<E: Evaluator>(searcher: &mut Searcher<E>, random_plies: usize) -> Vec<PackedPosition> {
+    let mut board = random_opening(random_plies);
+
+    let mut positions = Vec::new();
+    let (mut plies, mut decisive_plies) = (0, 0);
+
+    let outcome = loop {
+        let side = board.active_color();
+
+        if board.generate_moves().is_empty() {
+            break match board.in_check() {
+                true => winner(side.opposite()),
+                false => GameOutcome::Draw,
+            };
+        }
+
+        if board.is_rule_draw() {
+            break GameOutcome::Draw;
+        }
+
+        // only kings left can't be anything but a draw
+        if board.all_pieces().count_bits() == 2 {
+            break GameOutcome::Draw;
+        }
+
+        if plies >= MAX_PLIES {
+            break GameOutcome::Draw;
+        }
+
+        // a search stopped before finishing its first depth has no move to play, so the game is dropped
+        let Some((mov, score)) = searcher.best_move(&mut board) else {
+            return Vec::new();
+        };
+
+        let white_score = match side {
+            Color::White => score,
+            Color::Black => -score,
+        };
+
+        if score.abs() >= WIN_SCORE {
+            decisive_plies += 1;
+
+            if decisive_plies >= ADJUDICATION_PLIES {
+                break match white_score > 0 {
+                    true => GameOutcome::WhiteWin,
+                    false => GameOutcome::BlackWin,
+                };
+            }
+        } else {
+            decisive_plies = 0;
+
+            if !board.in_check() && !mov.is_capture() {
+                positions.push(PackedPosition::pack(&board, white_score, GameOutcome::Draw));
+            }
+        }
+
+        board.make_move(mov);
+        plies += 1;
+    };
+
+    positions
+        .into_iter()
+        .map(|position| position.with_outcome(outcome))
+        .collect()
+}
+
+/// Plays random moves from the starting position, starting over if the game ends before they are all played
+fn random_opening(random_plies: usize) -> Board {
+    let mut rng = rand::thread_rng();
+
+    'opening: loop {
+        let mut board = Board::default();
+
+        // vary the length a little, so that either side can be the one to move first after the opening
+        for _ in 0..random_plies + rng.gen_range(0..2) {
+            match board.generate_moves().choose(&mut rng) {
+                Some(mov) => board.make_move(*mov),
+                None => continue 'opening,
+            }
+        }
+
+        if !board.generate_moves().is_empty() {
+            return board;
+        }
+    }
+}
+
+/// Outcome of a game won by the given color
+fn winner(color: Color) -> GameOutcome {
+    match color {
+        Color::White => GameOutcome::WhiteWin,
+        Color::Black => GameOutcome::BlackWin,
+    }
+}
+
+/// Converts a binary training data file into the text format read by the trainer, returning the amount of positions
+pub fn convert_to_text(input: &str, output: &str) -> io::Result<usize> {
+    let reader = PositionReader::new(BufReader::new(File::open(input)?))?;
+    let mut writer = BufWriter::new(File::create(output)?);
+    let mut count = 0;
+
+    for position in reader {
+        writeln!(writer, "{}", position?.to_text())?;
+        count += 1;
+    }
+
+    writer.flush()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_datagen() {
+        // unique to this process, so that concurrent test runs don't share the file
+        let id = std::process::id();
+        let path = std::env::temp_dir().join(format!("otter_datagen_test_{}.bin", id));
+        let text = std::env::temp_dir().join(format!("otter_datagen_test_{}.txt", id));
+        let (path, text) = (path.to_str().unwrap(), text.to_str().unwrap());
+
+        let config = DatagenConfig {
+            games: 2,
+            nodes: 200,
+            random_plies: 8,
+            output: path.to_string(),
+        };
+        let count = generate(&config, None).unwrap();
+        assert!(count > 0);
+
+        // every recorded position is quiet, and the text conversion has one line for each
+        let file = BufReader::new(File::open(path).unwrap());
+        for position in PositionReader::new(file).unwrap() {
+            let board = Board::new(&position.unwrap().to_fen());
+            assert!(!board.in_check());
+        }
+
+        assert_eq!(convert_to_text(path, text).unwrap(), count);
+        assert_eq!(
+            std::fs::read_to_string(text).unwrap().lines().count(),
+            count
+        );
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(text).unwrap();
+    }
+}
@@ -0,0 +1,321 @@
+use crate::{
+    board::Board,
+    core::{Bitboard, Color, ALGEBRAIC_NOTATION, ALL_PIECES, BOARD_SIZE},
+    search::Score,
+};
+use std::io::{self, ErrorKind, Read, Write};
+
+/// Identifies training data files, followed by a format version
+const FILE_MAGIC: &[u8; 4] = b"ODG\x01";
+
+/// Size of a single packed position in bytes
+const PACKED_SIZE: usize = 29;
+
+/// Castling rights in the order of their bits in the packed flags
+const CASTLING: [char; 4] = ['K', 'Q', 'k', 'q'];
+
+/// Marks a packed position without an en passant square
+const NO_EN_PASSANT: u8 = 64;
+
+/// Outcome of a finished game, from white's point of view
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameOutcome {
+    BlackWin,
+    Draw,
+    WhiteWin,
+}
+
+impl GameOutcome {
+    /// Result as used for training, where 1 is a white win, 0.5 a draw and 0 a black win
+    fn as_result(self) -> f32 {
+        match self {
+            GameOutcome::BlackWin => 0.0,
+            GameOutcome::Draw => 0.5,
+            GameOutcome::WhiteWin => 1.0,
+        }
+    }
+}
+
+/// Position from a self-play game stored in 29 bytes, along with its search score and the result of its game
+///
+/// Pieces are stored as one 4-bit code per occupied square (color in the high bit, piece type below), in the order the
+/// occupancy bitboard is iterated.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PackedPosition {
+    occupancy: u64,
+    pieces: [u8; 16],
+    flags: u8,      // side to move in the lowest bit, castling rights in the next four
+    en_passant: u8, // en passant square, or `NO_EN_PASSANT`
+    score: Score,   // from white's point of view
+    outcome: GameOutcome,
+}
+
+impl PackedPosition {
+    /// Packs a board with its search score from white's point of view and the outcome of its game
+    pub fn pack(board: &Board, score: Score, outcome: GameOutcome) -> PackedPosition {
+        let occupancy = board.all_pieces();
+        let mut pieces = [0; 16];
+
+        for (i, square) in occupancy.enumerate() {
+            let color = match board.color_board(Color::White).bit_at(square) {
+                true => Color::White,
+                false => Color::Black,
+            };
+            let code = ((color as u8) << 3) | board.piece_at(square).unwrap() as u8;
+
+            pieces[i / 2] |= code << (4 * (i % 2));
+        }
+
+        // castling rights and the en passant square are taken straight from the FEN fields
+        let fen = board.to_fen();
+        let fields = fen.split(' ').collect::<Vec<_>>();
+
+        let mut flags = (board.active_color() == Color::Black) as u8;
+        for (i, right) in CASTLING.iter().enumerate() {
+            if fields[2].contains(*right) {
+                flags |= 1 << (i + 1);
+            }
+        }
+
+        let en_passant = ALGEBRAIC_NOTATION
+            .iter()
+            .position(|square| *square == fields[3])
+            .map_or(NO_EN_PASSANT, |square| square as u8);
+
+        PackedPosition {
+            occupancy: occupancy.0,
+            pieces,
+            flags,
+            en_passant,
+            score,
+            outcome,
+        }
+    }
+
+    /// Replaces the outcome of the game the position is from, once it is known
+    pub fn with_outcome(self, outcome: GameOutcome) -> PackedPosition {
+        PackedPosition { outcome, ..self }
+    }
+
+    /// Builds the FEN string of the position, with the move counters reset
+    pub fn to_fen(self) -> String {
+        let mut symbols = ['1'; BOARD_SIZE];
+
+        for (i, square) in Bitboard(self.occupancy).enumerate() {
+            let code = (self.pieces[i / 2] >> (4 * (i % 2))) & 0xF;
+            let color = if code >> 3 == 0 {
+                Color::White
+            } else {
+                Color::Black
+            };
+            let piece = ALL_PIECES[(code & 0x7) as usize];
+
+            symbols[square] = color.to_char(char::from(piece));
+        }
+
+        // collapse each rank's empty squares into a single digit
+        let placement = symbols
+            .chunks(8)
+            .map(|rank| {
+                let mut row = String::new();
+                let mut empty = 0;
+
+                for symbol in rank {
+                    if *symbol == '1' {
+                        empty += 1;
+                        continue;
+                    }
+
+                    if empty > 0 {
+                        row.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    row.push(*symbol);
+                }
+
+                if empty > 0 {
+                    row.push_str(&empty.to_string());
+                }
+
+                row
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let castling = CASTLING
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.flags & (1 << (i + 1)) != 0)
+            .map(|(_, right)| *right)
+            .collect::<String>();
+
+        format!(
+            "{} {} {} {} 0 1",
+            placement,
+            if self.flags & 1 == 0 { 'w' } else { 'b' },
+            if castling.is_empty() {
+                "-".to_string()
+            } else {
+                castling
+            },
+            match self.en_passant {
+                NO_EN_PASSANT => "-",
+                square => ALGEBRAIC_NOTATION[square as usize],
+            }
+        )
+    }
+
+    /// Converts the position to a line of text in the form `[FEN] | [score] | [result]`, as read by the trainer
+    pub fn to_text(self) -> String {
+        format!(
+            "{} | {} | {:.1}",
+            self.to_fen(),
+            self.score,
+            self.outcome.as_result()
+        )
+    }
+
+    /// Serializes the position as: occupancy, pieces, flags, en passant square, score, outcome (integers little
+    /// endian)
+    fn to_bytes(self) -> [u8; PACKED_SIZE] {
+        let mut bytes = [0; PACKED_SIZE];
+
+        bytes[0..8].copy_from_slice(&self.occupancy.to_le_bytes());
+        bytes[8..24].copy_from_slice(&self.pieces);
+        bytes[24] = self.flags;
+        bytes[25] = self.en_passant;
+        bytes[26..28].copy_from_slice(&self.score.to_le_bytes());
+        bytes[28] = self.outcome as u8;
+
+        bytes
+    }
+
+    /// Deserializes a position, returning `None` if it is invalid
+    fn from_bytes(bytes: &[u8; PACKED_SIZE]) -> Option<PackedPosition> {
+        let outcome = match bytes[28] {
+            0 => GameOutcome::BlackWin,
+            1 => GameOutcome::Draw,
+            2 => GameOutcome::WhiteWin,
+            _ => return None,
+        };
+
+        let position = PackedPosition {
+            occupancy: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            pieces: bytes[8..24].try_into().unwrap(),
+            flags: bytes[24],
+            en_passant: bytes[25],
+            score: Score::from_le_bytes([bytes[26], bytes[27]]),
+            outcome,
+        };
+
+        // every stored piece code must be a real piece
+        let valid = (0..position.occupancy.count_ones() as usize)
+            .all(|i| (position.pieces[i / 2] >> (4 * (i % 2))) & 0x7 < ALL_PIECES.len() as u8);
+
+        (valid && position.occupancy.count_ones() <= 32 && position.en_passant <= NO_EN_PASSANT)
+            .then_some(position)
+    }
+}
+
+/// Writes packed positions to a training data file, starting with its header
+pub struct PositionWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PositionWriter<W> {
+    pub fn new(mut writer: W) -> io::Result<PositionWriter<W>> {
+        writer.write_all(FILE_MAGIC)?;
+        Ok(PositionWriter { writer })
+    }
+
+    pub fn write(&mut self, position: &PackedPosition) -> io::Result<()> {
+        self.writer.write_all(&position.to_bytes())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads packed positions one at a time from a training data file, after checking its header
+pub struct PositionReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> PositionReader<R> {
+    pub fn new(mut reader: R) -> io::Result<PositionReader<R>> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+
+        if magic != *FILE_MAGIC {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "not an otter training data file",
+            ));
+        }
+
+        Ok(PositionReader { reader })
+    }
+}
+
+impl<R: Read> Iterator for PositionReader<R> {
+    type Item = io::Result<PackedPosition>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut bytes = [0; PACKED_SIZE];
+
+        // the file can only end cleanly between positions
+        match self.reader.read_exact(&mut bytes) {
+            Ok(()) => (),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return None,
+            Err(e) => return Some(Err(e)),
+        }
+
+        Some(
+            PackedPosition::from_bytes(&bytes)
+                .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "invalid packed position")),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packed_positions() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ];
+
+        let mut bytes = Vec::new();
+        let mut writer = PositionWriter::new(&mut bytes).unwrap();
+
+        for (fen, outcome) in fens.iter().zip([
+            GameOutcome::WhiteWin,
+            GameOutcome::Draw,
+            GameOutcome::BlackWin,
+        ]) {
+            let position = PackedPosition::pack(&Board::new(fen), -35, outcome);
+            assert_eq!(position.to_fen(), *fen);
+            writer.write(&position).unwrap();
+        }
+
+        assert_eq!(bytes.len(), FILE_MAGIC.len() + fens.len() * PACKED_SIZE);
+
+        // reading the file back gives the same positions, in the text format read by the trainer
+        let positions = PositionReader::new(bytes.as_slice())
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(positions.len(), fens.len());
+        assert_eq!(positions[0].to_text(), format!("{} | -35 | 1.0", fens[0]));
+        assert_eq!(positions[2].to_text(), format!("{} | -35 | 0.0", fens[2]));
+
+        // files of another format are rejected
+        assert!(PositionReader::new(b"OTB\x01".as_slice()).is_err());
+    }
+}
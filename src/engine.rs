@@ -1,9 +1,24 @@
 use crate::{
-    board::{perft_divide, Board, Magic},
+    board::{perft_divide, Board, Magic, Move},
+    book::{Book, BookLearning, GameResult},
     core::{Color, NUM_COLORS},
-    search::Searcher,
+    datagen::{self, DatagenConfig},
+    nnue::{Network, NnueEvaluator},
+    search::{
+        symmetry_mismatches, ClassicalEvaluator, EvalParams, Evaluator, Score, Searcher,
+        EVAL_CACHE_SIZE,
+    },
+    tablebase::{Tablebase, MAX_PIECES},
+    tuner::Tuner,
+};
+use std::{
+    fmt::Display,
+    fs,
+    io::{self, stdin},
+    sync::Arc,
+    thread::{self, JoinHandle},
+    time::Duration,
 };
-use std::{io::stdin, thread, time::Duration};
 
 /// Default transposition table size (in MB)
 const TT_SIZE: usize = 512;
@@ -11,21 +26,74 @@ const TT_SIZE: usize = 512;
 /// Maximum search time allowed to limit endless searching
 const MAX_SEARCH_TIME: Duration = std::time::Duration::from_secs(5);
 
+/// File containing opening lines for the book, built-in lines are used if it doesn't exist
+const BOOK_FILE: &str = "book.txt";
+
+/// File storing the results of previously played book lines
+const LEARNING_FILE: &str = "otter.learn";
+
+/// Directory containing endgame tablebase files
+const TABLEBASE_DIR: &str = "tablebases";
+
+/// File that tuned evaluation weights are written to
+const TUNED_PARAMS_FILE: &str = "tuned.toml";
+
+/// Default limit on the amount of passes the tuner makes over every weight
+const TUNE_ITERATIONS: usize = 100;
+
+/// File that self-play training data is written to
+const DATAGEN_FILE: &str = "selfplay.bin";
+
+/// Default amount of positions searched for each move of a self-play game
+const DATAGEN_NODES: u64 = 5000;
+
+/// Random moves played at the start of each self-play game
+const DATAGEN_RANDOM_PLIES: usize = 8;
+
+/// Largest evaluation cache size (in MB) allowed by the `EvalCache` option
+const MAX_EVAL_CACHE_SIZE: usize = 1024;
+
+/// Default file the evaluation network is loaded from
+const EVAL_FILE: &str = "otter.nnue";
+
 pub struct Engine {
     board: Board,
-    searcher: Searcher,
+    searcher: Searcher<EngineEvaluator>,
+
+    // opening book and its record of past performance
+    book: Book,
+    learning: BookLearning,
+
+    // color the engine is playing and the outcome of the current game once it has ended, used to update book learning
+    color: Option<Color>,
+    result: Option<GameResult>,
 
     // time controls per side
     time: [Duration; NUM_COLORS],
+
+    // file the network is loaded from when it is used for evaluation
+    eval_file: String,
+
+    // endgame tablebases being generated in the background, used for searching once they are done
+    tablebase_generation: Option<JoinHandle<Tablebase>>,
 }
 
 impl Engine {
     /// Generates a new engine, initializing a board and transposition table
     pub fn new() -> Engine {
+        let mut searcher = Searcher::with_evaluator(TT_SIZE, EngineEvaluator::default());
+        searcher.set_tablebase(Tablebase::load(TABLEBASE_DIR));
+
         Engine {
             board: Board::default(),
-            searcher: Searcher::new(TT_SIZE),
+            searcher,
+            book: Book::load(BOOK_FILE),
+            learning: BookLearning::load(LEARNING_FILE),
+            color: None,
+            result: None,
             time: [Duration::MAX; 2], // start out with no time limit
+            eval_file: EVAL_FILE.to_string(),
+            tablebase_generation: None,
         }
     }
 
@@ -38,6 +106,8 @@ impl Engine {
             let mut command = String::new();
             stdin().read_line(&mut command).unwrap();
 
+            self.install_generated_tablebase();
+
             // start reading through the tokens in the command
             let mut tokens = command.trim().split(' ');
 
@@ -46,16 +116,36 @@ impl Engine {
                     // print out some info about the engine
                     println!("id name Otter 1.0");
                     println!("id author Nick Wentworth");
+                    println!("option name UseNNUE type check default false");
+                    println!("option name EvalFile type string default {}", EVAL_FILE);
+                    println!("option name EvalParams type string default <empty>");
+                    println!(
+                        "option name EvalCache type spin default {} min 0 max {}",
+                        EVAL_CACHE_SIZE, MAX_EVAL_CACHE_SIZE
+                    );
                     println!("uciok");
                 }
 
                 Some("ucinewgame") => {
+                    // learn from the previous game's book moves before starting the next one
+                    self.finish_game();
+
                     // refresh transposition table when a new game is started
                     self.searcher.reset_tt(TT_SIZE);
                 }
 
                 Some("isready") => println!("readyok"),
 
+                Some("setoption") => {
+                    // options are given in the form "name [name] value [value]", where both can contain spaces
+                    let option = tokens.collect::<Vec<_>>().join(" ");
+
+                    if let Some(option) = option.strip_prefix("name ") {
+                        let (name, value) = option.split_once(" value ").unwrap_or((option, ""));
+                        self.set_option(name.trim(), value.trim());
+                    }
+                }
+
                 Some("position") => match tokens.next() {
                     // given a fen string
                     Some("fen") => {
@@ -72,14 +162,9 @@ impl Engine {
                         // set board to starting position
                         self.board = Board::default();
 
-                        while let Some(move_string) = tokens.next() {
+                        for move_string in tokens.by_ref() {
                             // try to find this move string from all current legal move strings
-                            match self
-                                .board
-                                .generate_moves()
-                                .into_iter()
-                                .find(|mov| mov.to_string() == move_string)
-                            {
+                            match self.board.parse_move(move_string) {
                                 Some(legal_move) => self.board.make_move(legal_move),
                                 None => println!("{} is not a legal move!", move_string),
                             }
@@ -106,6 +191,16 @@ impl Engine {
                         }
                     }
 
+                    // the engine plays the side it is asked to move for, which game results are recorded for
+                    self.color = Some(self.board.active_color());
+
+                    // play straight from the opening book if this position is in it
+                    if let Some(mov) = self.book.probe(&self.board, &self.learning) {
+                        self.learning.record_book_move(self.board.zobrist(), mov);
+                        println!("bestmove {}", mov);
+                        continue;
+                    }
+
                     // calculate how much time we can search for (estimating about 30 moves to be played at this speed)
                     let total_time = self.time[self.board.active_color()];
                     let search_time = Duration::min(total_time / 30, MAX_SEARCH_TIME);
@@ -122,16 +217,57 @@ impl Engine {
 
                     // find best move according to given parameters and print it to stdout
                     match self.searcher.best_move(&mut self.board) {
-                        Some((mov, _)) => println!("bestmove {}", mov),
+                        Some((mov, score)) => {
+                            self.learning.record_search(score);
+                            println!("bestmove {}", mov);
+                        }
                         None => println!("no moves in this position"),
                     }
                 }
 
+                Some("quit") => {
+                    self.finish_game();
+                    break;
+                }
+
                 // -------------------- non-uci commands -------------------- //
 
+                // result of the current game as given by the GUI, for games that end without a mate or draw on the board
+                Some("result") => match tokens.next() {
+                    Some("1-0") => self.record_result(Some(Color::White)),
+                    Some("0-1") => self.record_result(Some(Color::Black)),
+                    Some("1/2-1/2") => self.record_result(None),
+                    _ => println!("Please provide a result of 1-0, 0-1 or 1/2-1/2."),
+                },
+
                 // diplay board info
                 Some("display") => println!("{}", self.board),
 
+                // display every term of the static evaluation
+                Some("eval") => {
+                    let evaluator = self.searcher.evaluator();
+                    println!("{}", evaluator.classical.trace(&self.board));
+
+                    // the network can't be broken down into terms, so only its score is shown
+                    if let Some(nnue) = &mut evaluator.nnue {
+                        println!(
+                            "NNUE evaluation: {} (side to move), used instead",
+                            nnue.evaluate(&self.board)
+                        );
+                    }
+                }
+
+                // check that every position of an EPD file is evaluated the same as its mirrored positions
+                Some("evalsym") => match tokens.next() {
+                    Some(path) => {
+                        if let Err(e) = self.check_eval_symmetry(path) {
+                            println!("Unable to read positions: {}", e);
+                        }
+                    }
+
+                    None => println!("Please provide an EPD file of positions."),
+                },
+
                 // display transposition table statistics
                 Some("stats") => println!("{}", self.searcher),
 
@@ -151,13 +287,123 @@ impl Engine {
                 // generate a new set of magic numbers
                 Some("generate") => Magic::generate_magics(),
 
+                // generate endgame tablebases and start using them
+                Some("tbgen") => {
+                    let max_pieces = match tokens.next() {
+                        Some(token) => token.parse().ok(),
+                        None => Some(MAX_PIECES),
+                    };
+
+                    match max_pieces {
+                        _ if self.tablebase_generation.is_some() => {
+                            println!("Tablebases are already being generated.")
+                        }
+
+                        // generation takes a while, so it runs on its own thread to keep the engine responsive
+                        Some(max_pieces @ 3..=MAX_PIECES) => {
+                            self.tablebase_generation = Some(thread::spawn(move || {
+                                let tablebase = Tablebase::generate(max_pieces);
+
+                                match tablebase.save(TABLEBASE_DIR) {
+                                    Ok(()) => println!("Saved tablebases to {}", TABLEBASE_DIR),
+                                    Err(e) => println!("Unable to save tablebases: {}", e),
+                                }
+
+                                tablebase
+                            }))
+                        }
+
+                        _ => println!("Please provide a piece count from 3 to {}.", MAX_PIECES),
+                    }
+                }
+
+                // tune evaluation weights against a dataset of labeled positions
+                Some("tune") => match tokens.next() {
+                    Some(path) => {
+                        let iterations = tokens
+                            .next()
+                            .and_then(|token| token.parse().ok())
+                            .unwrap_or(TUNE_ITERATIONS);
+
+                        match Tuner::load(path) {
+                            Ok(mut tuner) => {
+                                println!("Loaded {} positions", tuner.len());
+                                println!("Fitted K = {}", tuner.fit_k());
+
+                                match tuner.tune(iterations, TUNED_PARAMS_FILE) {
+                                    Ok(()) => println!("Saved tuned weights to {}", TUNED_PARAMS_FILE),
+                                    Err(e) => println!("Unable to save tuned weights: {}", e),
+                                }
+                            }
+                            Err(e) => println!("Unable to load tuning dataset: {}", e),
+                        }
+                    }
+
+                    None => println!("Please provide a dataset to tune with."),
+                },
+
+                // play self-play games to generate training data
+                Some("datagen") => match tokens.next().and_then(|token| token.parse().ok()) {
+                    Some(games) => {
+                        let config = DatagenConfig {
+                            games,
+                            nodes: tokens
+                                .next()
+                                .and_then(|token| token.parse().ok())
+                                .unwrap_or(DATAGEN_NODES),
+                            random_plies: DATAGEN_RANDOM_PLIES,
+                            output: DATAGEN_FILE.to_string(),
+                        };
+
+                        let network = self
+                            .searcher
+                            .evaluator()
+                            .nnue
+                            .as_ref()
+                            .map(|nnue| Arc::clone(nnue.network()));
+
+                        match datagen::generate(&config, network) {
+                            Ok(count) => println!("Saved {} positions to {}", count, DATAGEN_FILE),
+                            Err(e) => println!("Unable to save training data: {}", e),
+                        }
+                    }
+
+                    None => println!("Please provide an amount of games to play."),
+                },
+
+                // convert binary training data to text
+                Some("convert") => match (tokens.next(), tokens.next()) {
+                    (Some(input), Some(output)) => match datagen::convert_to_text(input, output) {
+                        Ok(count) => println!("Converted {} positions to {}", count, output),
+                        Err(e) => println!("Unable to convert training data: {}", e),
+                    },
+
+                    _ => println!("Please provide an input and output file."),
+                },
+
                 // display common commands
                 Some("help") => {
                     println!();
                     println!("position fen [FEN]\n\tSetup board from fen string\n");
                     println!("go\n\tSearch for best move from current position\n");
+                    println!("result [1-0|0-1|1/2-1/2]\n\tRecord the result of the current game for book learning\n");
                     println!("display\n\tDisplay current position on the board\n");
+                    println!("eval\n\tDisplay every term of the static evaluation of the current position\n");
+                    println!("evalsym [file]\n\tCheck that positions from an EPD file are evaluated the same with colors or files mirrored\n");
                     println!("perft [depth]\n\tCount total number of permutations from the current position\n");
+                    println!(
+                        "tbgen [pieces]\n\tGenerate endgame tablebases in the background for up to {} pieces, or the given amount\n",
+                        MAX_PIECES
+                    );
+                    println!(
+                        "tune [dataset] [iterations]\n\tTune evaluation weights to the game results of a dataset of positions, saving them to {}\n",
+                        TUNED_PARAMS_FILE
+                    );
+                    println!(
+                        "datagen [games] [nodes]\n\tPlay self-play games searching a fixed amount of nodes per move, saving quiet positions to {}\n",
+                        DATAGEN_FILE
+                    );
+                    println!("convert [input] [output]\n\tConvert binary training data to text lines of \"FEN | score | result\"\n");
                 }
 
                 // if unable to match a command, do nothing
@@ -167,4 +413,206 @@ impl Engine {
             }
         }
     }
+
+    /// Starts searching with tablebases generated in the background, once their generation has finished
+    fn install_generated_tablebase(&mut self) {
+        if !self
+            .tablebase_generation
+            .as_ref()
+            .is_some_and(|handle| handle.is_finished())
+        {
+            return;
+        }
+
+        match self.tablebase_generation.take().unwrap().join() {
+            Ok(tablebase) => self.searcher.set_tablebase(tablebase),
+            Err(_) => println!("Tablebase generation failed"),
+        }
+    }
+
+    /// Applies a UCI option sent with the `setoption` command
+    fn set_option(&mut self, name: &str, value: &str) {
+        match name {
+            "UseNNUE" => match value {
+                "true" => self.load_network(),
+                _ => self.searcher.evaluator().nnue = None,
+            },
+
+            "EvalFile" => {
+                self.eval_file = value.to_string();
+
+                // switch over to the new network straight away if one is already in use
+                if self.searcher.evaluator().nnue.is_some() {
+                    self.load_network();
+                }
+            }
+
+            "EvalParams" => {
+                if let Err(e) = self.load_params(value) {
+                    println!("info string Unable to load evaluation weights {}: {}", value, e);
+                }
+            }
+
+            "EvalCache" => match value.parse() {
+                Ok(size) if size <= MAX_EVAL_CACHE_SIZE => {
+                    self.searcher.evaluator().classical.set_cache_size(size)
+                }
+                _ => println!("info string Invalid EvalCache size {}", value),
+            },
+
+            _ => println!("info string Unknown option {}", name),
+        }
+    }
+
+    /// Loads the network from the current eval file, falling back to the hand-crafted evaluation if it can't be loaded
+    fn load_network(&mut self) {
+        self.searcher.evaluator().nnue = match Network::load(&self.eval_file) {
+            Ok(network) => Some(NnueEvaluator::new(Arc::new(network))),
+            Err(e) => {
+                println!("info string Unable to load network {}: {}", self.eval_file, e);
+                None
+            }
+        };
+    }
+
+    /// Replaces the weights of the hand-crafted evaluation with those from a file, or the defaults if no file is given
+    ///
+    /// The current weights are kept if the file can't be loaded.
+    pub fn load_params(&mut self, path: &str) -> io::Result<()> {
+        let params = match path {
+            "" | "<empty>" => EvalParams::default(),
+            path => EvalParams::load(path)?,
+        };
+
+        self.searcher.evaluator().classical.set_params(params);
+        Ok(())
+    }
+
+    /// Evaluates every position of an EPD file along with its mirrored positions, printing those scored differently
+    fn check_eval_symmetry(&mut self, path: &str) -> io::Result<()> {
+        let (mut checked, mut mismatched) = (0, 0);
+
+        for line in fs::read_to_string(path)?.lines() {
+            // EPD lines start with the first four fields of a FEN string, the operations after them aren't needed
+            let fields = line.split_whitespace().take(4).collect::<Vec<_>>();
+            let fen = format!("{} 0 1", fields.join(" "));
+
+            let Some(board) = Board::try_new(&fen) else {
+                if !line.trim().is_empty() {
+                    println!("Skipping invalid position: {}", line);
+                }
+                continue;
+            };
+
+            let (score, mismatches) = symmetry_mismatches(self.searcher.evaluator(), &board);
+
+            for (mirrored, mirrored_score) in mismatches.iter() {
+                println!(
+                    "{} scores {}, but mirrored {} scores {}",
+                    fen,
+                    score,
+                    mirrored.to_fen(),
+                    mirrored_score
+                );
+            }
+
+            checked += 1;
+            mismatched += !mismatches.is_empty() as usize;
+        }
+
+        println!("Checked {} positions, {} evaluated asymmetrically", checked, mismatched);
+        Ok(())
+    }
+
+    /// Records the result of the current game from the engine's point of view, given the winning color or `None` for
+    /// a draw
+    fn record_result(&mut self, winner: Option<Color>) {
+        if let Some(color) = self.color {
+            self.result = Some(GameResult::for_side(winner, color));
+        }
+    }
+
+    /// Updates book learning with the outcome of the current game, if the engine has played in one
+    ///
+    /// A result given by the GUI is used if there is one, otherwise the game must have ended on the board with a mate
+    /// or a draw. Book moves of games with an unknown result are forgotten instead of being learned from.
+    fn finish_game(&mut self) {
+        if self.result.is_none() {
+            if self.board.generate_moves().is_empty() {
+                let winner = self.board.in_check().then(|| self.board.inactive_color());
+                self.record_result(winner);
+            } else if self.board.is_rule_draw() {
+                self.record_result(None);
+            }
+        }
+
+        match self.result.take() {
+            Some(result) => self.learning.finish_game(result),
+            None => self.learning.abandon_game(),
+        }
+
+        self.color = None;
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine::new()
+    }
+}
+
+/// Evaluation used by the engine's searcher, which is the hand-crafted one unless a network is enabled with `UseNNUE`
+///
+/// The hand-crafted evaluation keeps its weights and caches while a network is in use, so nothing is lost by switching
+/// back to it.
+#[derive(Default)]
+struct EngineEvaluator {
+    classical: ClassicalEvaluator,
+    nnue: Option<NnueEvaluator>,
+}
+
+impl EngineEvaluator {
+    /// Evaluator currently scoring positions for the search
+    fn active(&mut self) -> &mut dyn Evaluator {
+        match &mut self.nnue {
+            Some(nnue) => nnue,
+            None => &mut self.classical,
+        }
+    }
+}
+
+impl Evaluator for EngineEvaluator {
+    fn evaluate(&mut self, board: &Board) -> Score {
+        self.active().evaluate(board)
+    }
+
+    fn start_search(&mut self, board: &Board) {
+        self.active().start_search(board);
+    }
+
+    fn end_search(&mut self, board: &Board) {
+        self.active().end_search(board);
+    }
+
+    fn make_move(&mut self, board: &Board, mov: Move) {
+        self.active().make_move(board, mov);
+    }
+
+    fn unmake_move(&mut self, board: &Board) {
+        self.active().unmake_move(board);
+    }
+
+    fn make_null_move(&mut self, board: &Board) {
+        self.active().make_null_move(board);
+    }
+
+    fn unmake_null_move(&mut self, board: &Board) {
+        self.active().unmake_null_move(board);
+    }
+}
+
+impl Display for EngineEvaluator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.classical)
+    }
 }
@@ -0,0 +1,9 @@
+mod board;
+mod book;
+mod core;
+mod datagen;
+pub mod engine;
+pub mod nnue;
+pub mod search;
+mod tablebase;
+mod tuner;
@@ -1,9 +1,28 @@
-mod board;
-mod core;
-mod engine;
-mod search;
+use otter::engine::Engine;
+use std::{env, process};
+
+const USAGE: &str = "Usage: otter [--params FILE]";
 
 fn main() {
-    let mut e = engine::Engine::new();
+    let mut e = Engine::new();
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            // evaluation weights to use instead of the compiled-in defaults
+            ("--params", Some(path)) => {
+                if let Err(err) = e.load_params(&path) {
+                    eprintln!("Unable to load evaluation weights {}: {}", path, err);
+                    process::exit(1);
+                }
+            }
+
+            _ => {
+                eprintln!("{}", USAGE);
+                process::exit(1);
+            }
+        }
+    }
+
     e.uci();
 }
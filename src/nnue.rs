@@ -0,0 +1,275 @@
+use crate::{
+    board::{Board, Move, MoveFlag},
+    core::{Color, Piece, Square},
+    search::{Evaluator, Score},
+};
+use std::sync::Arc;
+
+mod network;
+pub mod trainer;
+
+pub use network::Network;
+
+use network::{feature_index, Accumulator};
+
+/// Piece of a given color standing on a square, which is a single active feature for each perspective
+type PieceFeature = (Piece, Color, Square);
+
+/// Evaluates positions with a network, keeping a stack of accumulators that follows the moves made by the search
+///
+/// Each move only adds and removes the features of the pieces it touches, except for a king move, which changes every
+/// feature of its own side and so refreshes that side's accumulator from scratch. Positions evaluated outside of a
+/// search have their accumulators computed from scratch.
+pub struct NnueEvaluator {
+    network: Arc<Network>,
+    stack: Vec<[Accumulator; 2]>, // accumulators of white and black's perspective after each move of the search
+}
+
+impl NnueEvaluator {
+    pub fn new(network: Arc<Network>) -> NnueEvaluator {
+        NnueEvaluator {
+            network,
+            stack: Vec::new(),
+        }
+    }
+
+    /// Network used for the evaluation
+    pub fn network(&self) -> &Arc<Network> {
+        &self.network
+    }
+
+    /// Computes the accumulators of both perspectives from scratch
+    fn refresh(&self, board: &Board) -> [Accumulator; 2] {
+        [
+            self.network.refresh(board, Color::White),
+            self.network.refresh(board, Color::Black),
+        ]
+    }
+
+    /// Pushes the accumulators for the position after a move, which must already be made on the board
+    fn push(&mut self, board: &Board, mov: Move, moving_color: Color) {
+        let (added, removed) = Self::changed_features(mov, moving_color);
+        let mut accumulators = *self.stack.last().unwrap();
+
+        for perspective in [Color::White, Color::Black] {
+            let accumulator = &mut accumulators[perspective as usize];
+
+            if mov.piece == Piece::King && perspective == moving_color {
+                *accumulator = self.network.refresh(board, perspective);
+                continue;
+            }
+
+            let king = board
+                .piece_board(Piece::King, perspective)
+                .get_first_square();
+            let index =
+                |(piece, color, square)| feature_index(perspective, king, piece, color, square);
+
+            for feature in added.into_iter().flatten() {
+                self.network.add_feature(accumulator, index(feature));
+            }
+
+            for feature in removed.into_iter().flatten() {
+                self.network.remove_feature(accumulator, index(feature));
+            }
+        }
+
+        self.stack.push(accumulators);
+    }
+
+    /// Runs the network on the accumulators of a position, from the given side's point of view
+    fn evaluate_accumulators(&self, accumulators: &[Accumulator; 2], side: Color) -> Score {
+        self.network.evaluate(
+            &accumulators[side as usize],
+            &accumulators[side.opposite() as usize],
+        )
+    }
+
+    /// Features that a move activates and deactivates, at most two of each
+    fn changed_features(
+        mov: Move,
+        moving_color: Color,
+    ) -> ([Option<PieceFeature>; 2], [Option<PieceFeature>; 2]) {
+        use MoveFlag::*;
+
+        let enemy_color = moving_color.opposite();
+        let moved = Some((mov.piece, moving_color, mov.from));
+
+        match mov.flag {
+            Quiet | PawnDoubleMove(_) => (
+                [Some((mov.piece, moving_color, mov.to)), None],
+                [moved, None],
+            ),
+            Promotion(promoted) => (
+                [Some((promoted, moving_color, mov.to)), None],
+                [moved, None],
+            ),
+            Capture(captured) => (
+                [Some((mov.piece, moving_color, mov.to)), None],
+                [moved, Some((captured, enemy_color, mov.to))],
+            ),
+            CapturePromotion(captured, promoted) => (
+                [Some((promoted, moving_color, mov.to)), None],
+                [moved, Some((captured, enemy_color, mov.to))],
+            ),
+            EnPassantCapture(enemy_pawn_square) => (
+                [Some((Piece::Pawn, moving_color, mov.to)), None],
+                [moved, Some((Piece::Pawn, enemy_color, enemy_pawn_square))],
+            ),
+            KingCastle => (
+                [
+                    Some((Piece::King, moving_color, mov.to)),
+                    Some((Piece::Rook, moving_color, mov.to - 1)),
+                ],
+                [moved, Some((Piece::Rook, moving_color, mov.to + 1))],
+            ),
+            QueenCastle => (
+                [
+                    Some((Piece::King, moving_color, mov.to)),
+                    Some((Piece::Rook, moving_color, mov.to + 1)),
+                ],
+                [moved, Some((Piece::Rook, moving_color, mov.to - 2))],
+            ),
+        }
+    }
+}
+
+impl Evaluator for NnueEvaluator {
+    fn evaluate(&mut self, board: &Board) -> Score {
+        let side = board.active_color();
+
+        match self.stack.last() {
+            Some(accumulators) => self.evaluate_accumulators(accumulators, side),
+            None => self.evaluate_accumulators(&self.refresh(board), side),
+        }
+    }
+
+    fn start_search(&mut self, board: &Board) {
+        self.stack = vec![self.refresh(board)];
+    }
+
+    fn end_search(&mut self, _board: &Board) {
+        self.stack.clear();
+    }
+
+    fn make_move(&mut self, board: &Board, mov: Move) {
+        self.push(board, mov, board.inactive_color());
+    }
+
+    fn unmake_move(&mut self, _board: &Board) {
+        self.stack.pop();
+    }
+
+    fn make_null_move(&mut self, _board: &Board) {
+        // no piece moves, only the side to move changes, which the evaluation reads from the board
+        self.stack.push(*self.stack.last().unwrap());
+    }
+
+    fn unmake_null_move(&mut self, _board: &Board) {
+        self.stack.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::Searcher;
+    use network::{FILE_MAGIC, HIDDEN_SIZE, NUM_FEATURES};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Builds a network file with small random weights
+    fn random_network_bytes() -> Vec<u8> {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut bytes = FILE_MAGIC.to_vec();
+
+        bytes.extend((HIDDEN_SIZE as u32).to_le_bytes());
+
+        for _ in 0..NUM_FEATURES * HIDDEN_SIZE + HIDDEN_SIZE {
+            bytes.extend(rng.gen_range(-64i16..64).to_le_bytes());
+        }
+
+        for _ in 0..2 * HIDDEN_SIZE {
+            bytes.push(rng.gen_range(-64i8..64) as u8);
+        }
+
+        bytes.extend(rng.gen_range(-1000i32..1000).to_le_bytes());
+        bytes
+    }
+
+    /// Walks every line to the given depth, checking the incrementally updated accumulators against fresh ones
+    fn check_accumulators(board: &mut Board, evaluator: &mut NnueEvaluator, depth: u8) {
+        let fresh = evaluator.refresh(board);
+        assert!(evaluator.stack.last() == Some(&fresh), "{}", board.to_fen());
+
+        if depth == 0 {
+            return;
+        }
+
+        for m in board.generate_moves() {
+            board.make_move(m);
+            evaluator.make_move(board, m);
+            check_accumulators(board, evaluator, depth - 1);
+            board.unmake_move();
+            evaluator.unmake_move(board);
+        }
+    }
+
+    #[test]
+    fn test_incremental_accumulators() {
+        let network = Arc::new(Network::from_bytes(&random_network_bytes()).unwrap());
+        let mut evaluator = NnueEvaluator::new(network);
+
+        // positions with castling, en passant, promotions and captures of promoting pawns available
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let mut board = Board::new(fen);
+            evaluator.start_search(&board);
+            check_accumulators(&mut board, &mut evaluator, 2);
+
+            // once the search is over, positions are evaluated from scratch
+            let score = evaluator.evaluate(&board);
+            evaluator.end_search(&board);
+            assert_eq!(evaluator.evaluate(&board), score);
+        }
+
+        // the network is only used through the searcher it is given to
+        let fen = "4k3/8/8/3r4/8/8/8/3RK3 w - - 0 1";
+        let mut searcher = Searcher::with_evaluator(1, evaluator);
+        searcher.set_node_limit(Some(2000));
+        *searcher.get_search_control().lock().unwrap() = true;
+
+        assert!(searcher.best_move(&mut Board::new(fen)).is_some());
+        assert!(searcher.evaluator().stack.is_empty());
+    }
+
+    #[test]
+    fn test_network_file() {
+        let bytes = random_network_bytes();
+        assert!(Network::from_bytes(&bytes).is_ok());
+
+        // truncated files and files of another format are rejected
+        assert!(Network::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Network::from_bytes(b"OTB\x01").is_err());
+
+        // a mirrored position gets the same evaluation for the side to move
+        let mut evaluator = NnueEvaluator::new(Arc::new(Network::from_bytes(&bytes).unwrap()));
+        assert_eq!(
+            evaluator.evaluate(&Board::new("4k3/3q4/8/8/2P5/8/8/4K2R w K - 0 1")),
+            evaluator.evaluate(&Board::new("4k2r/8/8/2p5/8/8/3Q4/4K3 b k - 0 1"))
+        );
+
+        // the largest weights a file can hold are summed without overflowing
+        let mut extreme = FILE_MAGIC.to_vec();
+        extreme.extend((HIDDEN_SIZE as u32).to_le_bytes());
+        let weights = NUM_FEATURES * HIDDEN_SIZE + HIDDEN_SIZE;
+        extreme.extend(i16::MAX.to_le_bytes().repeat(weights));
+        extreme.extend([i8::MAX as u8].repeat(2 * HIDDEN_SIZE));
+        extreme.extend(i32::MAX.to_le_bytes());
+
+        let mut evaluator = NnueEvaluator::new(Arc::new(Network::from_bytes(&extreme).unwrap()));
+        assert!(evaluator.evaluate(&Board::default()) > 0);
+    }
+}
@@ -0,0 +1,229 @@
+use crate::{
+    board::Board,
+    core::{Color, Piece, Square, BOARD_SIZE, NUM_COLORS, NUM_PIECES},
+    search::Score,
+};
+use std::{
+    fs,
+    io::{self, ErrorKind},
+};
+
+/// Identifies network files, followed by a format version
+pub const FILE_MAGIC: &[u8; 4] = b"ONN\x01";
+
+/// Amount of inputs to the network, one for every (own king square, piece color, piece type, piece square)
+pub const NUM_FEATURES: usize = BOARD_SIZE * NUM_COLORS * NUM_PIECES * BOARD_SIZE;
+
+/// Amount of neurons in the accumulator of each perspective
+pub const HIDDEN_SIZE: usize = 128;
+
+/// Quantisation factor of the feature weights and biases, which is also the upper bound of the clipped ReLU
+pub const QA: i32 = 255;
+
+/// Quantisation factor of the output weights
+pub const QB: i32 = 64;
+
+/// Scales the output of the network from win probability logits to centipawns
+pub const SCALE: i32 = 400;
+
+/// Network scores are clamped to this value, keeping them well clear of checkmate scores
+const MAX_SCORE: i64 = 10000;
+
+/// First layer outputs of a single perspective, before the activation is applied
+///
+/// Weights are stored as int16, but summed as int32 so that no combination of weights from a network file can overflow.
+pub type Accumulator = [i32; HIDDEN_SIZE];
+
+/// Quantised HalfKA network: a sparse first layer over (king square, piece) features for each side, whose clipped
+/// outputs for both sides feed a single output neuron
+///
+/// Feature weights and biases are stored as int16 scaled by `QA`, output weights as int8 scaled by `QB` and the output
+/// bias as int32 scaled by `QA * QB`.
+pub struct Network {
+    pub(super) feature_weights: Vec<i16>, // grouped by feature, `HIDDEN_SIZE` weights each
+    pub(super) feature_bias: Vec<i16>,
+    pub(super) output_weights: Vec<i8>, // weights of the side to move's accumulator first, then the other side's
+    pub(super) output_bias: i32,
+}
+
+impl Network {
+    /// Loads a network from the given file
+    pub fn load(path: &str) -> io::Result<Network> {
+        Network::from_bytes(&fs::read(path)?)
+    }
+
+    /// Writes the network to the given file
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    /// Serializes the network in the same form that `from_bytes` reads
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = FILE_MAGIC.to_vec();
+
+        bytes.extend((HIDDEN_SIZE as u32).to_le_bytes());
+
+        for weight in self.feature_weights.iter().chain(self.feature_bias.iter()) {
+            bytes.extend(weight.to_le_bytes());
+        }
+
+        bytes.extend(self.output_weights.iter().map(|weight| *weight as u8));
+        bytes.extend(self.output_bias.to_le_bytes());
+        bytes
+    }
+
+    /// Deserializes a network in the form: magic, hidden size, feature weights, feature biases, output weights, output
+    /// bias (all integers little endian)
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Network> {
+        let invalid = |message: String| io::Error::new(ErrorKind::InvalidData, message);
+
+        if bytes.get(..4) != Some(FILE_MAGIC.as_slice()) {
+            return Err(invalid("not an otter network file".to_string()));
+        }
+
+        let hidden_size = bytes
+            .get(4..8)
+            .map(|slice| u32::from_le_bytes(slice.try_into().unwrap()) as usize)
+            .ok_or_else(|| invalid("file is truncated".to_string()))?;
+
+        if hidden_size != HIDDEN_SIZE {
+            return Err(invalid(format!(
+                "network has {} hidden neurons, expected {}",
+                hidden_size, HIDDEN_SIZE
+            )));
+        }
+
+        let expected_len =
+            8 + 2 * NUM_FEATURES * HIDDEN_SIZE + 2 * HIDDEN_SIZE + 2 * HIDDEN_SIZE + 4;
+
+        if bytes.len() != expected_len {
+            return Err(invalid(format!(
+                "file is {} bytes, expected {}",
+                bytes.len(),
+                expected_len
+            )));
+        }
+
+        let mut data = &bytes[8..];
+        let mut take = |len: usize| {
+            let (taken, rest) = data.split_at(len);
+            data = rest;
+            taken
+        };
+
+        let read_i16s = |bytes: &[u8]| {
+            bytes
+                .chunks(2)
+                .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>()
+        };
+
+        let feature_weights = read_i16s(take(2 * NUM_FEATURES * HIDDEN_SIZE));
+        let feature_bias = read_i16s(take(2 * HIDDEN_SIZE));
+        let output_weights = take(2 * HIDDEN_SIZE)
+            .iter()
+            .map(|byte| *byte as i8)
+            .collect();
+        let output_bias = i32::from_le_bytes(take(4).try_into().unwrap());
+
+        Ok(Network {
+            feature_weights,
+            feature_bias,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    /// Computes the accumulator of one perspective from scratch
+    pub fn refresh(&self, board: &Board, perspective: Color) -> Accumulator {
+        let mut accumulator = [0; HIDDEN_SIZE];
+
+        for (value, bias) in accumulator.iter_mut().zip(&self.feature_bias) {
+            *value = *bias as i32;
+        }
+
+        for feature in active_features(board, perspective) {
+            self.add_feature(&mut accumulator, feature);
+        }
+
+        accumulator
+    }
+
+    /// Adds the weights of a feature that has become active to an accumulator
+    pub fn add_feature(&self, accumulator: &mut Accumulator, feature: usize) {
+        let weights = &self.feature_weights[feature * HIDDEN_SIZE..(feature + 1) * HIDDEN_SIZE];
+
+        for (value, weight) in accumulator.iter_mut().zip(weights) {
+            *value += *weight as i32;
+        }
+    }
+
+    /// Removes the weights of a feature that is no longer active from an accumulator
+    pub fn remove_feature(&self, accumulator: &mut Accumulator, feature: usize) {
+        let weights = &self.feature_weights[feature * HIDDEN_SIZE..(feature + 1) * HIDDEN_SIZE];
+
+        for (value, weight) in accumulator.iter_mut().zip(weights) {
+            *value -= *weight as i32;
+        }
+    }
+
+    /// Runs the output layer on the accumulators of the side to move and the other side, giving a score for the side
+    /// to move
+    pub fn evaluate(&self, own: &Accumulator, other: &Accumulator) -> Score {
+        let (own_weights, other_weights) = self.output_weights.split_at(HIDDEN_SIZE);
+
+        // clipped ReLU keeps every activation between 0 and QA
+        let layer = |accumulator: &Accumulator, weights: &[i8]| {
+            accumulator
+                .iter()
+                .zip(weights)
+                .map(|(value, weight)| (*value).clamp(0, QA) as i64 * *weight as i64)
+                .sum::<i64>()
+        };
+
+        // the output bias can be anywhere in its range, so the sum is widened to keep it from overflowing
+        let output =
+            layer(own, own_weights) + layer(other, other_weights) + self.output_bias as i64;
+
+        let score = output * SCALE as i64 / (QA * QB) as i64;
+        score.clamp(-MAX_SCORE, MAX_SCORE) as Score
+    }
+}
+
+/// Index of the input activated by a piece on a square, as seen by one perspective
+///
+/// Each perspective sees the board from its own side, so black's squares are flipped vertically and pieces are
+/// labeled as its own or the enemy's
+pub fn feature_index(
+    perspective: Color,
+    king: Square,
+    piece: Piece,
+    color: Color,
+    square: Square,
+) -> usize {
+    let orient = |square: Square| match perspective {
+        Color::White => square,
+        Color::Black => square ^ 56,
+    };
+
+    let relative_color = (color != perspective) as usize;
+
+    ((orient(king) * NUM_COLORS + relative_color) * NUM_PIECES + piece as usize) * BOARD_SIZE
+        + orient(square)
+}
+
+/// Indices of every input activated by the pieces on the board, as seen by one perspective
+pub fn active_features(board: &Board, perspective: Color) -> impl Iterator<Item = usize> + '_ {
+    let king = board
+        .piece_board(Piece::King, perspective)
+        .get_first_square();
+
+    [Color::White, Color::Black]
+        .into_iter()
+        .flat_map(move |color| {
+            board.color_board(color).map(move |square| {
+                let piece = board.piece_at(square).unwrap();
+                feature_index(perspective, king, piece, color, square)
+            })
+        })
+}
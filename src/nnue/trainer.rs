@@ -0,0 +1,481 @@
+use super::network::{active_features, Network, HIDDEN_SIZE, NUM_FEATURES, QA, QB, SCALE};
+use crate::{board::Board, core::Color};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use std::{fs, io, ops::Range, thread, time::Instant};
+
+// offsets of each layer within the flattened parameters, the feature weights come first
+const FEATURE_BIAS: usize = NUM_FEATURES * HIDDEN_SIZE;
+const OUTPUT_WEIGHTS: usize = FEATURE_BIAS + HIDDEN_SIZE;
+const OUTPUT_BIAS: usize = OUTPUT_WEIGHTS + 2 * HIDDEN_SIZE;
+const NUM_PARAMS: usize = OUTPUT_BIAS + 1;
+
+/// Every weight is kept within this range, so that the quantised output weights fit in an int8 and the accumulators
+/// can't overflow an int16
+const WEIGHT_CLIP: f32 = 1.98;
+
+// Adam hyperparameters besides the learning rate
+const BETA1: f32 = 0.9;
+const BETA2: f32 = 0.999;
+const EPSILON: f32 = 1e-8;
+
+/// Settings for a training run
+pub struct TrainingConfig {
+    pub epochs: usize,
+    pub batch_size: usize,
+    pub learning_rate: f32,
+    pub wdl: f32, // weight of the game result in the target, the search score makes up the rest
+    pub validation: f32, // fraction of the positions held out to measure validation loss
+    pub output: String, // file the quantised network is written to after every epoch
+}
+
+impl Default for TrainingConfig {
+    fn default() -> Self {
+        TrainingConfig {
+            epochs: 10,
+            batch_size: 16384,
+            learning_rate: 0.001,
+            wdl: 0.3,
+            validation: 0.05,
+            output: "otter.nnue".to_string(),
+        }
+    }
+}
+
+/// Position to train on, stored as the inputs it activates for each perspective
+struct Sample {
+    features: [Vec<usize>; 2], // side to move's perspective first, then the other side's
+    score: f32,                // search score in centipawns, from the side to move's point of view
+    result: f32,               // 1 for a win, 0.5 for a draw and 0 for a loss of the side to move
+}
+
+impl Sample {
+    /// Parses a line in the form `[FEN] | [score] | [result]`, where the score and result are from white's point of
+    /// view and the result is 1.0, 0.5 or 0.0
+    fn parse(line: &str) -> Option<Sample> {
+        let parts = line.split('|').map(str::trim).collect::<Vec<_>>();
+
+        if parts.len() != 3 {
+            return None;
+        }
+
+        let board = Board::try_new(parts[0])?;
+        let score = parts[1].parse::<f32>().ok()?;
+        let result = parts[2].parse::<f32>().ok()?;
+
+        let side = board.active_color();
+        let (score, result) = match side == Color::White {
+            true => (score, result),
+            false => (-score, 1.0 - result),
+        };
+
+        Some(Sample {
+            features: [side, side.opposite()]
+                .map(|perspective| active_features(&board, perspective).collect()),
+            score,
+            result,
+        })
+    }
+
+    /// Expected result the network is trained towards, blending the game result with the search score
+    fn target(&self, wdl: f32) -> f32 {
+        wdl * self.result + (1.0 - wdl) * sigmoid(self.score / SCALE as f32)
+    }
+}
+
+/// Gradients of every parameter summed over part of a batch, keeping track of which feature rows were touched
+struct Gradients {
+    values: Vec<f32>,
+    touched: Vec<bool>,
+    rows: Vec<usize>,
+}
+
+impl Gradients {
+    fn new() -> Gradients {
+        Gradients {
+            values: vec![0.0; NUM_PARAMS],
+            touched: vec![false; NUM_FEATURES],
+            rows: Vec::new(),
+        }
+    }
+
+    /// Adds to the gradient of every weight of a single feature
+    fn add_row(&mut self, feature: usize, deltas: &[f32]) {
+        if !self.touched[feature] {
+            self.touched[feature] = true;
+            self.rows.push(feature);
+        }
+
+        let row = &mut self.values[feature * HIDDEN_SIZE..(feature + 1) * HIDDEN_SIZE];
+
+        for (value, delta) in row.iter_mut().zip(deltas) {
+            *value += delta;
+        }
+    }
+
+    /// Moves every gradient into another set of gradients, leaving these zeroed
+    fn drain_into(&mut self, other: &mut Gradients) {
+        for feature in self.rows.drain(..) {
+            let range = feature * HIDDEN_SIZE..(feature + 1) * HIDDEN_SIZE;
+            other.add_row(feature, &self.values[range.clone()]);
+
+            self.values[range].fill(0.0);
+            self.touched[feature] = false;
+        }
+
+        for index in FEATURE_BIAS..NUM_PARAMS {
+            other.values[index] += self.values[index];
+            self.values[index] = 0.0;
+        }
+    }
+}
+
+/// Trains the network architecture used by the engine on positions labeled with search scores and game results
+///
+/// The network is trained in floating point with Adam to minimise the squared error between its predicted result and
+/// a blend of the game result and the result expected from the search score. Only the rows of feature weights active
+/// in a batch are updated, as most of the inputs are inactive in any given position. After every epoch the network is
+/// quantised and written out in the format the engine loads.
+pub struct Trainer {
+    training: Vec<Sample>,
+    validation: Vec<Sample>,
+    config: TrainingConfig,
+
+    params: Vec<f32>,
+    momentum: Vec<f32>, // Adam's first moment estimates
+    velocity: Vec<f32>, // Adam's second moment estimates
+    step: i32,
+
+    rng: StdRng,
+}
+
+impl Trainer {
+    /// Loads a dataset with one labeled position per line, skipping (and reporting) any invalid lines
+    pub fn load(path: &str, config: TrainingConfig) -> io::Result<Trainer> {
+        let mut samples = Vec::new();
+
+        for line in fs::read_to_string(path)?.lines() {
+            match Sample::parse(line) {
+                Some(sample) => samples.push(sample),
+                None => println!("Skipping invalid training sample: {}", line),
+            }
+        }
+
+        Ok(Trainer::new(samples, config))
+    }
+
+    /// Sets up a randomly initialised network, holding out part of the shuffled samples for validation
+    fn new(mut samples: Vec<Sample>, config: TrainingConfig) -> Trainer {
+        let mut rng = StdRng::seed_from_u64(0);
+        samples.shuffle(&mut rng);
+
+        let validation_len = (samples.len() as f32 * config.validation) as usize;
+        let validation = samples.split_off(samples.len() - validation_len);
+
+        // small random weights, scaled down for the output layer which has many more active inputs
+        let mut params = vec![0.0; NUM_PARAMS];
+
+        for weight in params[..FEATURE_BIAS].iter_mut() {
+            *weight = rng.gen_range(-0.1..0.1);
+        }
+
+        for weight in params[OUTPUT_WEIGHTS..OUTPUT_BIAS].iter_mut() {
+            *weight = rng.gen_range(-0.05..0.05);
+        }
+
+        Trainer {
+            training: samples,
+            validation,
+            config,
+            params,
+            momentum: vec![0.0; NUM_PARAMS],
+            velocity: vec![0.0; NUM_PARAMS],
+            step: 0,
+            rng,
+        }
+    }
+
+    /// Amount of positions used for training and for validation
+    pub fn sample_counts(&self) -> (usize, usize) {
+        (self.training.len(), self.validation.len())
+    }
+
+    /// Runs every epoch, reporting the training and validation loss and saving the network after each one
+    pub fn train(&mut self) -> io::Result<()> {
+        let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+        let mut gradients = (0..threads).map(|_| Gradients::new()).collect::<Vec<_>>();
+        let mut total = Gradients::new();
+
+        for epoch in 1..=self.config.epochs {
+            let start = Instant::now();
+            let loss = self.train_epoch(&mut gradients, &mut total);
+
+            println!(
+                "Epoch {}: training loss {:.6}, validation loss {:.6} in {:.1}s",
+                epoch,
+                loss,
+                self.loss(&self.validation),
+                start.elapsed().as_secs_f32()
+            );
+
+            self.quantise().save(&self.config.output)?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs a single pass over the shuffled training set, returning the mean training loss
+    fn train_epoch(&mut self, gradients: &mut [Gradients], total: &mut Gradients) -> f64 {
+        let mut training = std::mem::take(&mut self.training);
+        training.shuffle(&mut self.rng);
+
+        let mut loss = 0.0;
+
+        for batch in training.chunks(self.config.batch_size) {
+            loss += self.backward(batch, gradients);
+
+            for thread_gradients in gradients.iter_mut() {
+                thread_gradients.drain_into(total);
+            }
+
+            self.update(total, batch.len());
+        }
+
+        self.training = training;
+        loss / self.training.len().max(1) as f64
+    }
+
+    /// Sums the gradients of a batch split between threads, returning the total loss of the batch
+    fn backward(&self, batch: &[Sample], gradients: &mut [Gradients]) -> f64 {
+        let chunk_size = batch.len().div_ceil(gradients.len()).max(1);
+        let (params, wdl) = (&self.params, self.config.wdl);
+
+        thread::scope(|scope| {
+            let handles = batch
+                .chunks(chunk_size)
+                .zip(gradients.iter_mut())
+                .map(|(samples, gradients)| {
+                    scope.spawn(move || {
+                        samples
+                            .iter()
+                            .map(|sample| backward_sample(params, sample, wdl, gradients))
+                            .sum::<f64>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .sum()
+        })
+    }
+
+    /// Takes a single Adam step with the averaged gradients of a batch, updating only the feature rows it touched
+    fn update(&mut self, gradients: &mut Gradients, batch_len: usize) {
+        self.step += 1;
+
+        let learning_rate = self.config.learning_rate;
+        let scale = 1.0 / batch_len as f32;
+        let corrections = (1.0 - BETA1.powi(self.step), 1.0 - BETA2.powi(self.step));
+
+        let mut adam = |index: usize| {
+            let gradient = gradients.values[index] * scale;
+            gradients.values[index] = 0.0;
+
+            self.momentum[index] = BETA1 * self.momentum[index] + (1.0 - BETA1) * gradient;
+            self.velocity[index] =
+                BETA2 * self.velocity[index] + (1.0 - BETA2) * gradient * gradient;
+
+            let momentum = self.momentum[index] / corrections.0;
+            let velocity = self.velocity[index] / corrections.1;
+
+            let weight =
+                self.params[index] - learning_rate * momentum / (velocity.sqrt() + EPSILON);
+            self.params[index] = weight.clamp(-WEIGHT_CLIP, WEIGHT_CLIP);
+        };
+
+        for feature in gradients.rows.drain(..) {
+            for index in feature * HIDDEN_SIZE..(feature + 1) * HIDDEN_SIZE {
+                adam(index);
+            }
+        }
+
+        for index in FEATURE_BIAS..NUM_PARAMS {
+            adam(index);
+        }
+
+        gradients.touched.fill(false);
+    }
+
+    /// Mean loss of the network over the given samples
+    fn loss(&self, samples: &[Sample]) -> f64 {
+        if samples.is_empty() {
+            return 0.0;
+        }
+
+        let total = samples
+            .iter()
+            .map(|sample| {
+                let error =
+                    sigmoid(forward(&self.params, sample).1) - sample.target(self.config.wdl);
+                (error * error) as f64
+            })
+            .sum::<f64>();
+
+        total / samples.len() as f64
+    }
+
+    /// Rounds every weight to the integer representation used by the engine
+    pub fn quantise(&self) -> Network {
+        let quantise = |range: Range<usize>, scale: i32| {
+            self.params[range]
+                .iter()
+                .map(|weight| (weight * scale as f32).round())
+                .collect::<Vec<_>>()
+        };
+
+        Network {
+            feature_weights: quantise(0..FEATURE_BIAS, QA)
+                .into_iter()
+                .map(|weight| weight as i16)
+                .collect(),
+            feature_bias: quantise(FEATURE_BIAS..OUTPUT_WEIGHTS, QA)
+                .into_iter()
+                .map(|weight| weight as i16)
+                .collect(),
+            output_weights: quantise(OUTPUT_WEIGHTS..OUTPUT_BIAS, QB)
+                .into_iter()
+                .map(|weight| weight as i8)
+                .collect(),
+            output_bias: (self.params[OUTPUT_BIAS] * (QA * QB) as f32).round() as i32,
+        }
+    }
+}
+
+/// Computes the accumulators of both perspectives and the raw output of the network, whose sigmoid is the expected
+/// result for the side to move
+fn forward(params: &[f32], sample: &Sample) -> ([[f32; HIDDEN_SIZE]; 2], f32) {
+    let mut accumulators = [[0.0; HIDDEN_SIZE]; 2];
+    let mut output = params[OUTPUT_BIAS];
+
+    for (perspective, accumulator) in accumulators.iter_mut().enumerate() {
+        accumulator.copy_from_slice(&params[FEATURE_BIAS..OUTPUT_WEIGHTS]);
+
+        for feature in sample.features[perspective].iter() {
+            let row = &params[feature * HIDDEN_SIZE..(feature + 1) * HIDDEN_SIZE];
+
+            for (value, weight) in accumulator.iter_mut().zip(row) {
+                *value += weight;
+            }
+        }
+
+        let weights = &params[OUTPUT_WEIGHTS + perspective * HIDDEN_SIZE..];
+
+        for (value, weight) in accumulator.iter().zip(weights) {
+            output += value.clamp(0.0, 1.0) * weight;
+        }
+    }
+
+    (accumulators, output)
+}
+
+/// Adds the gradients of a single sample's loss, returning that loss
+fn backward_sample(params: &[f32], sample: &Sample, wdl: f32, gradients: &mut Gradients) -> f64 {
+    let (accumulators, output) = forward(params, sample);
+    let prediction = sigmoid(output);
+    let error = prediction - sample.target(wdl);
+
+    // derivative of the squared error through the sigmoid
+    let output_gradient = 2.0 * error * prediction * (1.0 - prediction);
+    gradients.values[OUTPUT_BIAS] += output_gradient;
+
+    for (perspective, accumulator) in accumulators.iter().enumerate() {
+        let offset = OUTPUT_WEIGHTS + perspective * HIDDEN_SIZE;
+        let mut deltas = [0.0; HIDDEN_SIZE];
+
+        for (i, value) in accumulator.iter().enumerate() {
+            gradients.values[offset + i] += output_gradient * value.clamp(0.0, 1.0);
+
+            // the clipped ReLU only passes gradients through its linear part
+            if *value > 0.0 && *value < 1.0 {
+                deltas[i] = output_gradient * params[offset + i];
+            }
+        }
+
+        for (bias, delta) in gradients.values[FEATURE_BIAS..OUTPUT_WEIGHTS]
+            .iter_mut()
+            .zip(deltas)
+        {
+            *bias += delta;
+        }
+
+        for feature in sample.features[perspective].iter() {
+            gradients.add_row(*feature, &deltas);
+        }
+    }
+
+    (error * error) as f64
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{nnue::NnueEvaluator, search::Evaluator};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_training() {
+        let lines = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 | 20 | 0.5",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1 | -800 | 0.0",
+            "rnb1kbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1 | 850 | 1.0",
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 | 150 | 1.0",
+        ];
+        let samples = lines
+            .iter()
+            .filter_map(|line| Sample::parse(line))
+            .collect::<Vec<_>>();
+        assert_eq!(samples.len(), lines.len());
+
+        // scores and results are converted to the side to move's point of view
+        assert_eq!((samples[2].score, samples[2].result), (-850.0, 0.0));
+
+        let config = TrainingConfig {
+            epochs: 20,
+            batch_size: 2,
+            validation: 0.0,
+            ..Default::default()
+        };
+        let mut trainer = Trainer::new(samples, config);
+
+        let initial_loss = trainer.loss(&trainer.training);
+        let mut gradients = vec![Gradients::new()];
+        let mut total = Gradients::new();
+
+        for _ in 0..trainer.config.epochs {
+            trainer.train_epoch(&mut gradients, &mut total);
+        }
+
+        assert!(trainer.loss(&trainer.training) < initial_loss);
+
+        // the quantised network gives about the same scores as the trained one, within a few percent of rounding error
+        let mut evaluator = NnueEvaluator::new(Arc::new(trainer.quantise()));
+
+        for line in lines {
+            let board = Board::new(line.split('|').next().unwrap().trim());
+            let quantised = evaluator.evaluate(&board);
+            let expected = forward(&trainer.params, &Sample::parse(line).unwrap()).1 * SCALE as f32;
+
+            assert!(
+                (quantised as f32 - expected).abs() < 10.0 + expected.abs() * 0.05,
+                "{} vs {}",
+                quantised,
+                expected
+            );
+        }
+    }
+}
@@ -1,5 +1,17 @@
-use super::{evaluate::evaluate, ordering::order_moves, tt::TranspositionTable, Score};
-use crate::board::{Board, Move};
+use super::{
+    evaluator::{ClassicalEvaluator, Evaluator},
+    history::{HistoryTable, KillerMoves},
+    ordering::order_moves,
+    reductions::late_move_reduction,
+    see::see_ge,
+    tt::TranspositionTable,
+    Score,
+};
+use crate::{
+    board::{Board, Move},
+    core::Piece,
+    tablebase::{Outcome, Tablebase},
+};
 use std::{
     fmt::Display,
     sync::{Arc, Mutex},
@@ -14,6 +26,21 @@ const DRAW: Score = 0;
 /// Maximum depth allowed to be searched to
 const MAX_DEPTH: u8 = u8::MAX;
 
+// Null move pruning is only tried from this depth on, with a reduction that grows along with the depth
+const NULL_MOVE_MIN_DEPTH: u8 = 3;
+const NULL_MOVE_REDUCTION: u8 = 2;
+const NULL_MOVE_DEPTH_DIVISOR: u8 = 4;
+
+// Late move reductions only apply from this depth on, to moves after the first few in the ordered list
+const LMR_MIN_DEPTH: u8 = 3;
+const LMR_MIN_MOVES: usize = 3;
+
+/// Depth from which a null move cutoff is only trusted once a reduced search without null moves confirms it
+const NULL_MOVE_VERIFICATION_DEPTH: u8 = 8;
+
+/// Halfmove clock from which winning tablebase moves have to reset it, as the 50 move rule could draw the game first
+const TABLEBASE_HALFMOVE_LIMIT: u32 = 80;
+
 /// Transposition table used for searching, stores required data about scoring a position
 pub type SearchTT = TranspositionTable<ScoreData>;
 
@@ -34,16 +61,38 @@ pub struct ScoreData {
     best_move: Option<Move>, // if found, the current best move from this position
 }
 
-pub struct Searcher {
+/// Searches for the best move in a position, scoring the positions it reaches with the given evaluator
+pub struct Searcher<E: Evaluator = ClassicalEvaluator> {
     table: SearchTT,
+    tablebase: Tablebase, // endgame tables probed for positions with few pieces
+    evaluator: E,
+    killers: KillerMoves, // quiet moves that caused cutoffs at each ply of the current search
+    history: HistoryTable, // how often quiet moves caused cutoffs, kept between searches
     search_active: Arc<Mutex<bool>>, // variable to track if search should stay active or not
+    nodes: u64, // positions visited during the current search
+    node_limit: Option<u64>, // if set, the search stops after visiting this many positions
+    has_best_move: bool, // whether the current search has finished a depth, after which the node limit applies
 }
 
 impl Searcher {
+    /// Creates a searcher using the hand-crafted evaluation with its default weights
     pub fn new(tt_size: usize) -> Searcher {
+        Searcher::with_evaluator(tt_size, ClassicalEvaluator::default())
+    }
+}
+
+impl<E: Evaluator> Searcher<E> {
+    pub fn with_evaluator(tt_size: usize, evaluator: E) -> Searcher<E> {
         Searcher {
             table: SearchTT::new(tt_size),
+            tablebase: Tablebase::default(),
+            evaluator,
+            killers: KillerMoves::new(),
+            history: HistoryTable::new(),
             search_active: Arc::new(Mutex::new(false)),
+            nodes: 0,
+            node_limit: None,
+            has_best_move: false,
         }
     }
 
@@ -52,9 +101,38 @@ impl Searcher {
         self.table = SearchTT::new(tt_size);
     }
 
+    /// Replaces the endgame tablebase used during the search
+    pub fn set_tablebase(&mut self, tablebase: Tablebase) {
+        self.tablebase = tablebase;
+    }
+
+    /// Limits every following search to visiting a fixed amount of positions, or removes the limit if `None` is given
+    ///
+    /// The first depth is always searched in full, so that a move is found no matter how small the limit is
+    pub fn set_node_limit(&mut self, node_limit: Option<u64>) {
+        self.node_limit = node_limit;
+    }
+
+    /// Evaluator used to score the positions reached by the search
+    pub fn evaluator(&mut self) -> &mut E {
+        &mut self.evaluator
+    }
+
     /// Returns an estimation of the best move by recursively checking opponent's best response is to this move
     pub fn best_move(&mut self, board: &mut Board) -> Option<(Move, Score)> {
+        // if the result of every move is known from the tablebase, there is no need to search
+        if let Some(best) = self.tablebase_move(board) {
+            return Some(best);
+        }
+
         let mut best: Option<(Move, i16)> = None;
+        self.nodes = 0;
+        self.has_best_move = false;
+        self.evaluator.start_search(board);
+
+        // killers only relate to the positions of the previous search, while older history slowly fades out
+        self.killers.clear();
+        self.history.age();
 
         // iterative deepening - keep incrementing depth until an alloted search time is used up
         for depth in 1..MAX_DEPTH {
@@ -63,12 +141,12 @@ impl Searcher {
                 .generate_moves()
                 .into_iter()
                 .map(|mov| {
-                    board.make_move(mov);
-                    let score = -self.alpha_beta(board, -INFINITY, INFINITY, depth, 1);
-                    board.unmake_move();
+                    self.make_move(board, mov);
+                    let score = -self.alpha_beta(board, -INFINITY, INFINITY, depth, 1, true);
+                    self.unmake_move(board);
                     (mov, score)
                 })
-                .max_by_key(|(_, score)| score.clone()); // max by the score value
+                .max_by_key(|(_, score)| *score); // max by the score value
 
             // only assign result to the current best if the search was not terminated early
             match self.search_terminated() {
@@ -76,6 +154,8 @@ impl Searcher {
                 false => best = result,
             }
 
+            self.has_best_move = true;
+
             // leave early if we found a forced mate sequence
             if let Some((_, score)) = best {
                 if score.abs() > CHECKMATE_THRESHOLD {
@@ -84,10 +164,13 @@ impl Searcher {
             }
         }
 
+        self.evaluator.end_search(board);
         best
     }
 
     /// Recursive step of alpha beta algorithm
+    ///
+    /// A null move is only tried if `null_allowed` is set, which keeps null moves from being made twice in a row
     fn alpha_beta(
         &mut self,
         board: &mut Board,
@@ -95,6 +178,7 @@ impl Searcher {
         beta: Score,
         depth: u8,
         ply: u8,
+        null_allowed: bool,
     ) -> Score {
         use ScoreLimit::*;
 
@@ -102,14 +186,21 @@ impl Searcher {
             return 0;
         }
 
+        self.nodes += 1;
+
         // TODO - this may not always properly handle draws, as transposition table sees repetitions 1, 2, and 3 as the same hash
         if board.is_drawable() {
             return DRAW;
         }
 
+        // positions covered by the tablebase already have a perfect score
+        if let Some(outcome) = self.tablebase.probe(board) {
+            return Self::tablebase_score(outcome, ply);
+        }
+
         // base case - if depth is 0, evaluate the board state
         if depth == 0 {
-            return Self::quiesce(board, alpha, beta);
+            return self.quiesce(board, alpha, beta);
         }
 
         // check if this position has already been evaluated and is stored in the transposition table
@@ -148,6 +239,12 @@ impl Searcher {
             None => None,
         };
 
+        // if passing the turn still isn't enough for the opponent to stay below beta, a real move is very likely to
+        // fail high as well, so the position is pruned
+        if null_allowed && self.null_move_cutoff(board, beta, depth, ply) {
+            return beta;
+        }
+
         // else, generate moves and score them recursively
         let mut moves = board.generate_moves();
 
@@ -162,18 +259,55 @@ impl Searcher {
         }
 
         // order the moves based on approximate importance to help remove other bad moves early
-        order_moves(&mut moves, best_move);
+        let killers = self.killers.get(ply);
+        order_moves(&mut moves, board, best_move, killers, &self.history);
 
         // keep track of if this position's score is an upper bound or exact
         let mut flag = Alpha;
         let mut best_move = None;
 
+        // only moves searched with an open window can end up on the principal variation
+        let pv_node = alpha + 1 < beta;
+        let in_check = board.in_check();
+        let color = board.active_color();
+
+        // quiet moves searched so far that didn't cause a cutoff, which are penalized if a later one does
+        let mut quiets_tried = Vec::new();
+
         // go through the moves and find the best score
-        for mov in moves {
+        for (i, mov) in moves.into_iter().enumerate() {
             // make the move and get the enemy's best response to that move, in terms of our evaluation
-            board.make_move(mov);
-            let score = -self.alpha_beta(board, -beta, -alpha, depth - 1, ply + 1);
-            board.unmake_move();
+            self.make_move(board, mov);
+
+            // quiet moves late in the ordering rarely turn out best, so they are searched less deeply unless either
+            // side is in check
+            let reduction = match depth >= LMR_MIN_DEPTH
+                && i >= LMR_MIN_MOVES
+                && mov.is_quiet()
+                && !in_check
+                && !board.in_check()
+            {
+                true => late_move_reduction(
+                    depth,
+                    i,
+                    pv_node,
+                    killers.contains(&Some(mov)),
+                    self.history.get(color, mov),
+                ),
+                false => 0,
+            };
+
+            let mut score = match reduction {
+                0 => -self.alpha_beta(board, -beta, -alpha, depth - 1, ply + 1, true),
+                _ => -self.alpha_beta(board, -alpha - 1, -alpha, depth - 1 - reduction, ply + 1, true),
+            };
+
+            // a reduced move that beats alpha may be better than it looked, so it is searched again in full
+            if reduction > 0 && score > alpha {
+                score = -self.alpha_beta(board, -beta, -alpha, depth - 1, ply + 1, true);
+            }
+
+            self.unmake_move(board);
 
             // check that the search is not over
             if self.search_terminated() {
@@ -183,6 +317,16 @@ impl Searcher {
             // if the evaluation for this move is better than the opponent's current best option,
             // they won't allow this to happen, so this move wouldn't even be considered
             if score >= beta {
+                // a quiet move causing a cutoff is likely to cause one in sibling positions and later searches too
+                if mov.is_quiet() {
+                    self.killers.insert(ply, mov);
+                    self.history.reward(color, mov, depth);
+
+                    for quiet in quiets_tried {
+                        self.history.penalize(color, quiet, depth);
+                    }
+                }
+
                 // add this board configuration into the transposition table
                 self.table.insert(
                     board.zobrist(),
@@ -197,6 +341,10 @@ impl Searcher {
                 return beta;
             }
 
+            if mov.is_quiet() {
+                quiets_tried.push(mov);
+            }
+
             // update our current best move
             if score > alpha {
                 flag = Exact; // we now have an exact move score
@@ -220,12 +368,46 @@ impl Searcher {
         alpha
     }
 
+    /// Searches the position after passing the turn at a reduced depth, returning whether it still fails high
+    ///
+    /// Passing is the worst move in zugzwang, where every real move makes things worse, so null moves aren't tried
+    /// when only pawns are left to move. Deep cutoffs are verified with a reduced search of the real moves.
+    fn null_move_cutoff(&mut self, board: &mut Board, beta: Score, depth: u8, ply: u8) -> bool {
+        let pawns_and_king =
+            board.active_piece_board(Piece::Pawn) | board.active_piece_board(Piece::King);
+
+        // a null move is illegal while in check, and shouldn't be trusted to prove or refute a mate
+        if depth < NULL_MOVE_MIN_DEPTH
+            || board.active_pieces() == pawns_and_king
+            || board.in_check()
+            || beta.abs() >= CHECKMATE_THRESHOLD
+        {
+            return false;
+        }
+
+        let reduction = NULL_MOVE_REDUCTION + depth / NULL_MOVE_DEPTH_DIVISOR;
+        let reduced_depth = depth.saturating_sub(1 + reduction);
+
+        self.make_null_move(board);
+        let score = -self.alpha_beta(board, -beta, -beta + 1, reduced_depth, ply + 1, false);
+        self.unmake_null_move(board);
+
+        if self.search_terminated() || score < beta {
+            return false;
+        }
+
+        depth < NULL_MOVE_VERIFICATION_DEPTH
+            || self.alpha_beta(board, beta - 1, beta, reduced_depth, ply, false) >= beta
+    }
+
     /// Final step of alpha beta search, before evaluation we want to ensure that our moved piece is not about to be captured
     ///
     /// Searches down all capture-only paths until a quiet position is found for each
-    fn quiesce(board: &mut Board, mut alpha: Score, beta: Score) -> Score {
+    fn quiesce(&mut self, board: &mut Board, mut alpha: Score, beta: Score) -> Score {
+        self.nodes += 1;
+
         // first get the current board evaluation
-        let current_score = evaluate(board);
+        let current_score = self.evaluator.evaluate(board);
 
         // if the score of this board is higher than the best guarantee (worse for the previous color), they wouldn't make this capture
         if current_score >= beta {
@@ -236,13 +418,18 @@ impl Searcher {
         alpha = Score::max(alpha, current_score);
 
         let mut captures = board.generate_captures();
-        order_moves(&mut captures, None);
+        order_moves(&mut captures, board, None, [None; 2], &self.history);
 
         // this is same as alpha beta search
         for mov in captures {
-            board.make_move(mov);
-            let score = -Self::quiesce(board, -beta, -alpha);
-            board.unmake_move();
+            // captures that lose material once the opponent takes back are very unlikely to raise alpha
+            if !see_ge(board, mov, 0) {
+                continue;
+            }
+
+            self.make_move(board, mov);
+            let score = -self.quiesce(board, -beta, -alpha);
+            self.unmake_move(board);
 
             if score >= beta {
                 return beta;
@@ -254,6 +441,77 @@ impl Searcher {
         alpha
     }
 
+    /// Makes a move on the board during the search, letting the evaluator update its state
+    fn make_move(&mut self, board: &mut Board, mov: Move) {
+        board.make_move(mov);
+        self.evaluator.make_move(board, mov);
+    }
+
+    /// Unmakes the last move made during the search, letting the evaluator restore its state
+    fn unmake_move(&mut self, board: &mut Board) {
+        board.unmake_move();
+        self.evaluator.unmake_move(board);
+    }
+
+    /// Passes the turn during the search, letting the evaluator update its state
+    fn make_null_move(&mut self, board: &mut Board) {
+        board.make_null_move();
+        self.evaluator.make_null_move(board);
+    }
+
+    /// Unmakes the last null move made during the search, letting the evaluator restore its state
+    fn unmake_null_move(&mut self, board: &mut Board) {
+        board.unmake_null_move();
+        self.evaluator.unmake_null_move(board);
+    }
+
+    /// Picks the move leading to the best tablebase outcome, if every move from this position can be probed
+    ///
+    /// The tables don't know about repetitions or the 50 move rule, so winning moves that repeat a position, or that
+    /// don't reset the halfmove clock once it is close to the limit, are skipped. If that leaves no winning move, the
+    /// position is searched instead.
+    fn tablebase_move(&self, board: &mut Board) -> Option<(Move, Score)> {
+        let mut best: Option<(Move, Score)> = None;
+        let mut skipped_win = false;
+
+        let near_limit = board.halfmove_clock() >= TABLEBASE_HALFMOVE_LIMIT;
+
+        for mov in board.generate_moves() {
+            board.make_move(mov);
+            let outcome = self.tablebase.probe(board);
+            let repeated = board.is_repetition();
+            board.unmake_move();
+
+            // score is from the opponent's point of view after the move, at a ply of 1
+            let score = -Self::tablebase_score(outcome?, 1);
+
+            // a won game could still be drawn before the mate is reached, while other outcomes can't get any worse
+            let resets_clock = mov.piece == Piece::Pawn || mov.is_capture();
+            if score > DRAW && (repeated || (near_limit && !resets_clock)) {
+                skipped_win = true;
+                continue;
+            }
+
+            if best.is_none_or(|(_, best_score)| score > best_score) {
+                best = Some((mov, score));
+            }
+        }
+
+        match best {
+            Some((_, score)) if skipped_win && score <= DRAW => None,
+            best => best,
+        }
+    }
+
+    /// Converts a tablebase outcome into a search score, treating distances to mate the same as found checkmates
+    fn tablebase_score(outcome: Outcome, ply: u8) -> Score {
+        match outcome {
+            Outcome::Win(plies) => CHECKMATE - (ply as Score + plies as Score),
+            Outcome::Draw => DRAW,
+            Outcome::Loss(plies) => -CHECKMATE + (ply as Score + plies as Score),
+        }
+    }
+
     // TODO - this could likely be abstracted away from the engine or other class controlling the search
     /// Clones the `search_active` variable for this struct to allow external control of search time
     pub fn get_search_control(&self) -> Arc<Mutex<bool>> {
@@ -265,6 +523,7 @@ impl Searcher {
     /// Must be checked within searches to enforce strict time management
     fn search_terminated(&self) -> bool {
         !(*self.search_active.lock().unwrap())
+            || (self.has_best_move && self.node_limit.is_some_and(|limit| self.nodes >= limit))
     }
 
     // checkmates are stored in the transposition table as "mate in _ from this position" scores
@@ -291,8 +550,9 @@ impl Searcher {
     }
 }
 
-impl Display for Searcher {
+impl<E: Evaluator + Display> Display for Searcher<E> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.table)
+        write!(f, "{}", self.table)?;
+        write!(f, "{}", self.evaluator)
     }
 }
@@ -1,26 +1,243 @@
-use super::{pst::piece_square_table, Score};
-use crate::board::Board;
+use super::{tapered::Tapered, tt::TranspositionTable, Score};
+use crate::{
+    board::Board,
+    core::{Bitboard, Color, Piece, NUM_PIECES},
+};
+use attacks::AttackMaps;
+use king_safety::evaluate_king_safety;
+use material::{MaterialTable, MATERIAL_TABLE_SIZE};
+use mobility::evaluate_mobility;
+use params::EvalParams;
+use passed::evaluate_passed_pawns;
+use pawns::{PawnTable, PAWN_TABLE_SIZE};
+use pieces::evaluate_pieces;
+use scale::scale_endgame;
+use std::fmt::Display;
+use threats::evaluate_threats;
+
+pub use trace::EvalTrace;
+
+mod attacks;
+mod endgame;
+mod king_safety;
+mod masks;
+mod material;
+mod mobility;
+pub mod params;
+mod passed;
+mod pawns;
+mod pieces;
+mod scale;
+mod threats;
+mod trace;
+
+/// Default size of the evaluation cache in MB
+pub const EVAL_CACHE_SIZE: usize = 16;
+
+/// Largest score the hand-crafted evaluation can give, keeping evaluations with extreme weights far from mate scores
+const MAX_EVAL: Score = 10000;
+
+/// Hash table caching the final evaluation of positions, keyed by the full zobrist hash of the board
+type EvalCache = TranspositionTable<Score>;
+
+/// Caches of evaluations kept between positions, both of whole positions and of terms that only depend on part of one
+pub struct EvalTables {
+    pawns: PawnTable,
+    material: MaterialTable,
+    cache: EvalCache,
+    cache_size: usize, // size of the evaluation cache in MB
+}
+
+impl EvalTables {
+    pub fn new() -> EvalTables {
+        Self::with_cache_size(EVAL_CACHE_SIZE)
+    }
+
+    /// Tables with an evaluation cache of the given size in MB, where a size of 0 turns the cache off
+    pub fn with_cache_size(cache_size: usize) -> EvalTables {
+        EvalTables {
+            pawns: PawnTable::new(PAWN_TABLE_SIZE),
+            material: MaterialTable::new(MATERIAL_TABLE_SIZE),
+            cache: EvalCache::new(cache_size),
+            cache_size,
+        }
+    }
+
+    /// Tables that don't store anything, for evaluating with weights that keep changing
+    pub fn uncached() -> EvalTables {
+        EvalTables {
+            pawns: PawnTable::new(0),
+            material: MaterialTable::new(0),
+            cache: EvalCache::new(0),
+            cache_size: 0,
+        }
+    }
+
+    /// Size of the evaluation cache in MB
+    pub fn cache_size(&self) -> usize {
+        self.cache_size
+    }
+}
+
+impl Default for EvalTables {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for EvalTables {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "pawn table:")?;
+        write!(f, "{}", self.pawns)?;
+        writeln!(f, "material table:")?;
+        write!(f, "{}", self.material)?;
+        writeln!(f, "eval cache ({} MB):", self.cache_size)?;
+        write!(f, "{}", self.cache)
+    }
+}
 
 /// Evaluate the board position and assign a value representing the active side's advantage
-pub fn evaluate(board: &Board) -> Score {
-    // initialize values used for evaluation
-    let mut material = 0; // overall material advantage
-    let mut position = 0; // positional advantage
+pub fn evaluate(board: &Board, params: &EvalParams, tables: &mut EvalTables) -> Score {
+    // positions reached again through transpositions have already been evaluated
+    let hash = board.zobrist();
 
-    // for each side, add or subtract to values based on advantages
-    for active_square in board.active_pieces() {
-        let active_piece = board.piece_at(active_square).unwrap();
+    if let Some(score) = tables.cache.get(hash) {
+        return score;
+    }
 
-        material += active_piece.material_value();
-        position += piece_square_table(active_piece, board.active_color(), active_square);
+    let score = evaluate_position(board, params, tables);
+    tables.cache.insert(hash, score);
+
+    score
+}
+
+/// Evaluates the board position without looking in the evaluation cache
+fn evaluate_position(board: &Board, params: &EvalParams, tables: &mut EvalTables) -> Score {
+    // material changes even less often than pawns, and decides which specialised endgame function applies
+    let material = material::probe(board, &mut tables.material, &params.material);
+
+    // endgames with known winning techniques or drawn material are scored by specialised functions
+    if let Some(score) = material
+        .endgame
+        .and_then(|endgame| endgame.evaluate(board, &params.material))
+    {
+        return score;
     }
 
-    for inactive_square in board.inactive_pieces() {
-        let inactive_piece = board.piece_at(inactive_square).unwrap();
+    // pawn structure rarely changes, so it is usually already in the pawn table
+    let pawns = pawns::probe(board, &mut tables.pawns, &params.pawns);
+    let attacks = AttackMaps::new(board);
+
+    // each side is evaluated from white's point of view, then blended by how far the game has progressed
+    let score = evaluate_side(board, Color::White, pawns.passed, &attacks, params)
+        - evaluate_side(board, Color::Black, pawns.passed, &attacks, params)
+        + pawns.score
+        + material.imbalance;
+
+    // drawish endgames are pulled towards a draw so the engine doesn't trade into them while "winning"
+    let score = scale_endgame(board, score, pawns.passed)
+        .interpolate(material.phase)
+        .clamp(-MAX_EVAL, MAX_EVAL);
+
+    match board.active_color() {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+/// Amount of separately named terms in the evaluation of a single side
+const NUM_SIDE_TERMS: usize = 12;
+
+/// Evaluates the material, piece placement, mobility, piece features, threats, king safety and passed pawns of a single
+/// side
+fn evaluate_side(
+    board: &Board,
+    color: Color,
+    passed: Bitboard,
+    attacks: &AttackMaps,
+    params: &EvalParams,
+) -> Tapered {
+    side_terms(board, color, passed, attacks, params)
+        .into_iter()
+        .fold(Tapered::default(), |score, (_, term)| score + term)
+}
+
+/// Every term of a single side's evaluation along with its name, which add up to the score of that side
+fn side_terms(
+    board: &Board,
+    color: Color,
+    passed: Bitboard,
+    attacks: &AttackMaps,
+    params: &EvalParams,
+) -> [(&'static str, Tapered); NUM_SIDE_TERMS] {
+    let mut material = Tapered::default();
+    let mut pst = [Tapered::default(); NUM_PIECES];
 
-        material -= inactive_piece.material_value();
-        position -= piece_square_table(inactive_piece, board.inactive_color(), inactive_square);
+    for square in board.color_board(color) {
+        let piece = board.piece_at(square).unwrap();
+        material += params.material.values[piece as usize];
+        pst[piece as usize] += params.pst.get(piece, color, square);
     }
 
-    material + position
+    [
+        ("Material", material),
+        ("PST pawns", pst[Piece::Pawn as usize]),
+        ("PST knights", pst[Piece::Knight as usize]),
+        ("PST bishops", pst[Piece::Bishop as usize]),
+        ("PST rooks", pst[Piece::Rook as usize]),
+        ("PST queens", pst[Piece::Queen as usize]),
+        ("PST king", pst[Piece::King as usize]),
+        ("Mobility", evaluate_mobility(board, color, attacks, &params.mobility)),
+        ("Pieces", evaluate_pieces(board, color, attacks, &params.pieces)),
+        ("Threats", evaluate_threats(board, color, attacks, &params.threats)),
+        ("King safety", evaluate_king_safety(board, color, &params.king_safety)),
+        ("Passed pawns", evaluate_passed_pawns(board, color, passed, attacks, &params.passed)),
+    ]
+}
+
+/// Breaks the evaluation of the board position down into every term for each side and phase
+///
+/// The trace ends in the same score that `evaluate` gives, including when a specialised endgame function replaces the
+/// hand-crafted terms
+pub fn trace(board: &Board, params: &EvalParams, tables: &mut EvalTables) -> EvalTrace {
+    let material = material::probe(board, &mut tables.material, &params.material);
+    let pawns = pawns::probe(board, &mut tables.pawns, &params.pawns);
+    let attacks = AttackMaps::new(board);
+
+    let white = side_terms(board, Color::White, pawns.passed, &attacks, params);
+    let black = side_terms(board, Color::Black, pawns.passed, &attacks, params);
+
+    let mut terms = white
+        .into_iter()
+        .zip(black)
+        .map(|((name, white), (_, black))| (name, [white, black]))
+        .collect::<Vec<_>>();
+
+    // the pawn structure is cached for both sides together, so each side's part is evaluated again
+    terms.insert(
+        1,
+        (
+            "Pawn structure",
+            [Color::White, Color::Black].map(|color| pawns::evaluate_color(board, color, &params.pawns).0),
+        ),
+    );
+
+    let total = terms
+        .iter()
+        .fold(material.imbalance, |score, (_, [white, black])| score + *white - *black);
+
+    let replaced = material
+        .endgame
+        .and_then(|endgame| endgame.evaluate(board, &params.material))
+        .map(|score| ("Specialised endgame", score));
+
+    EvalTrace {
+        terms,
+        imbalance: material.imbalance,
+        total,
+        scaled: scale_endgame(board, total, pawns.passed),
+        phase: material.phase,
+        replaced,
+        side: board.active_color(),
+    }
 }
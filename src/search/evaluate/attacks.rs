@@ -0,0 +1,60 @@
+use crate::{
+    board::{Board, BISHOP_MAGICS, KING_MOVES, KNIGHT_MOVES, PAWN_ATTACKS, ROOK_MAGICS},
+    core::{Bitboard, Color, Piece, Square, ALL_PIECES, NUM_COLORS, NUM_PIECES},
+};
+
+/// Generates the squares attacked by a single piece, sliding pieces are stopped by any piece in `occupancy`
+pub fn piece_attacks(piece: Piece, color: Color, square: Square, occupancy: Bitboard) -> Bitboard {
+    use Piece::*;
+
+    match piece {
+        Pawn => PAWN_ATTACKS[color][square],
+        Knight => KNIGHT_MOVES[square],
+        Bishop => BISHOP_MAGICS[square].get(occupancy),
+        Rook => ROOK_MAGICS[square].get(occupancy),
+        Queen => BISHOP_MAGICS[square].get(occupancy) | ROOK_MAGICS[square].get(occupancy),
+        King => KING_MOVES[square],
+    }
+}
+
+/// Squares attacked by each piece type of each color, generated once per evaluation and shared between terms
+pub struct AttackMaps {
+    by_piece: [[Bitboard; NUM_PIECES]; NUM_COLORS],
+    all: [Bitboard; NUM_COLORS],
+}
+
+impl AttackMaps {
+    pub fn new(board: &Board) -> AttackMaps {
+        let occupancy = board.all_pieces();
+
+        let mut maps = AttackMaps {
+            by_piece: [[Bitboard::EMPTY; NUM_PIECES]; NUM_COLORS],
+            all: [Bitboard::EMPTY; NUM_COLORS],
+        };
+
+        for color in [Color::White, Color::Black] {
+            for piece in ALL_PIECES {
+                let attacks = board
+                    .piece_board(piece, color)
+                    .fold(Bitboard::EMPTY, |attacks, square| {
+                        attacks | piece_attacks(piece, color, square, occupancy)
+                    });
+
+                maps.by_piece[color as usize][piece as usize] = attacks;
+                maps.all[color] |= attacks;
+            }
+        }
+
+        maps
+    }
+
+    /// Squares attacked by any piece of the given type and color
+    pub fn by_piece(&self, piece: Piece, color: Color) -> Bitboard {
+        self.by_piece[color as usize][piece as usize]
+    }
+
+    /// Squares attacked by any piece of the given color
+    pub fn all(&self, color: Color) -> Bitboard {
+        self.all[color]
+    }
+}
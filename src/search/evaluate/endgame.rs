@@ -0,0 +1,254 @@
+use super::{material::MaterialParams, MAX_EVAL};
+use crate::{
+    board::Board,
+    core::{distance, file_of, is_light_square, rank_of, Color, Piece, Square},
+    search::Score,
+};
+
+mod kpk;
+
+/// Bonus given to positions that are known to be won, on top of the material advantage
+const KNOWN_WIN: Score = 1000;
+
+/// Score of positions that are known to be drawn
+const DRAW: Score = 0;
+
+/// Specialised evaluation of a position from the strong side's point of view, using the endgame values of the pieces
+///
+/// Returns `None` if the position isn't one that the function has any knowledge of
+type EndgameFn = fn(&Board, Color, &MaterialParams) -> Option<Score>;
+
+/// Endgame with a known evaluation function, matched by the material signature of the position
+#[derive(Clone, Copy)]
+pub struct Endgame {
+    function: EndgameFn,
+    strong: Color,
+}
+
+impl Endgame {
+    /// Finds a specialised evaluation function for the material on the board, if there is one
+    ///
+    /// All known endgames are against a lone king, so the side with the extra material is considered the strong side.
+    /// The choice only depends on the amount of each piece, so it can be cached by the material hash of the board
+    pub fn find(board: &Board) -> Option<Endgame> {
+        use Piece::*;
+
+        for strong in [Color::White, Color::Black] {
+            if board.color_board(strong.opposite()).count_bits() != 1 {
+                continue;
+            }
+
+            let count = |piece: Piece| board.piece_board(piece, strong).count_bits();
+            let pieces = count(Knight) + count(Bishop) + count(Rook) + count(Queen);
+
+            let function: EndgameFn = match count(Pawn) {
+                // pawnless endings are either mated by driving the king into a corner, or can't be won at all
+                0 if count(Knight) == 1 && count(Bishop) == 1 && pieces == 2 => kbnk,
+                0 => pawnless,
+
+                1 if pieces == 0 => kpk,
+                _ if count(Bishop) == 1 && pieces == 1 => wrong_rook_pawn,
+                _ => continue,
+            };
+
+            return Some(Endgame { function, strong });
+        }
+
+        None
+    }
+
+    /// Evaluates the position from the active side's point of view, or returns `None` to fall back to regular evaluation
+    pub fn evaluate(self, board: &Board, params: &MaterialParams) -> Option<Score> {
+        let score = (self.function)(board, self.strong, params)?.clamp(-MAX_EVAL, MAX_EVAL);
+
+        match board.active_color() == self.strong {
+            true => Some(score),
+            false => Some(-score),
+        }
+    }
+}
+
+/// Returns true if the pieces (excluding pawns) of the given side can force mate against a lone king
+fn has_mating_material(board: &Board, color: Color) -> bool {
+    use Piece::*;
+
+    let count = |piece: Piece| board.piece_board(piece, color).count_bits();
+
+    let bishops = board.piece_board(Bishop, color);
+    let light_bishop = bishops.into_iter().any(is_light_square);
+    let dark_bishop = bishops.into_iter().any(|square| !is_light_square(square));
+
+    count(Queen) + count(Rook) > 0
+        || (light_bishop && dark_bishop)
+        || (count(Bishop) > 0 && count(Knight) > 0)
+        || count(Knight) >= 3
+}
+
+/// Total endgame value of the given side's material
+fn material(board: &Board, color: Color, params: &MaterialParams) -> Score {
+    board
+        .color_board(color)
+        .map(|square| params.values[board.piece_at(square).unwrap() as usize].eg)
+        .fold(0, Score::saturating_add)
+}
+
+fn king_square(board: &Board, color: Color) -> Square {
+    board.piece_board(Piece::King, color).get_first_square()
+}
+
+/// Bonus for the weak king being close to the edges of the board, and even more so the corners
+fn push_to_edge(square: Square) -> Score {
+    let file_edge = file_of(square).min(7 - file_of(square));
+    let rank_edge = rank_of(square).min(7 - rank_of(square));
+
+    40 * (6 - file_edge - rank_edge) as Score
+}
+
+/// Bonus for the kings being close together, as the strong king is needed to help deliver mate
+fn push_close(a: Square, b: Square) -> Score {
+    20 * (7 - distance(a, b)) as Score
+}
+
+/// Pieces against a lone king are either mated by driving the king to the edge, or can't be won at all (KNK, KNNK)
+///
+/// Bishops of the same color can't mate, so this can't be decided from the amount of each piece alone
+fn pawnless(board: &Board, strong: Color, params: &MaterialParams) -> Option<Score> {
+    match has_mating_material(board, strong) {
+        true => mop_up(board, strong, params),
+        false => Some(DRAW),
+    }
+}
+
+/// Drives the weak king towards the edge of the board and brings the strong king closer to it
+fn mop_up(board: &Board, strong: Color, params: &MaterialParams) -> Option<Score> {
+    let strong_king = king_square(board, strong);
+    let weak_king = king_square(board, strong.opposite());
+
+    let bonus = KNOWN_WIN + push_to_edge(weak_king) + push_close(strong_king, weak_king);
+    Some(bonus.saturating_add(material(board, strong, params)))
+}
+
+/// Bishop and knight can only mate in a corner of the bishop's color, so the weak king is driven towards one
+fn kbnk(board: &Board, strong: Color, params: &MaterialParams) -> Option<Score> {
+    const LIGHT_CORNERS: [Square; 2] = [0, 63]; // a8 and h1
+    const DARK_CORNERS: [Square; 2] = [7, 56]; // h8 and a1
+
+    let strong_king = king_square(board, strong);
+    let weak_king = king_square(board, strong.opposite());
+    let bishop = board.piece_board(Piece::Bishop, strong).get_first_square();
+
+    let corners = match is_light_square(bishop) {
+        true => LIGHT_CORNERS,
+        false => DARK_CORNERS,
+    };
+
+    let corner_distance = corners
+        .into_iter()
+        .map(|corner| distance(weak_king, corner))
+        .min()
+        .unwrap();
+
+    let bonus = KNOWN_WIN + 50 * (7 - corner_distance) as Score + push_close(strong_king, weak_king);
+    Some(bonus.saturating_add(material(board, strong, params)))
+}
+
+/// Uses the bitbase to find whether the single pawn wins, rewarding progress of the pawn if it does
+fn kpk(board: &Board, strong: Color, params: &MaterialParams) -> Option<Score> {
+    // the bitbase is stored from white's point of view, so flip the ranks for black
+    let normalize = |square: Square| match strong {
+        Color::White => square,
+        Color::Black => square ^ 56,
+    };
+
+    let strong_king = normalize(king_square(board, strong));
+    let weak_king = normalize(king_square(board, strong.opposite()));
+    let pawn = normalize(board.piece_board(Piece::Pawn, strong).get_first_square());
+
+    match kpk::probe(strong_king, pawn, weak_king, board.active_color() == strong) {
+        true => {
+            let bonus = KNOWN_WIN + 10 * rank_of(pawn) as Score;
+            Some(bonus.saturating_add(params.values[Piece::Pawn as usize].eg))
+        }
+        false => Some(DRAW),
+    }
+}
+
+/// Rook pawns with a bishop that can't control the promotion square are drawn if the weak king reaches the corner
+fn wrong_rook_pawn(board: &Board, strong: Color, _params: &MaterialParams) -> Option<Score> {
+    let pawns = board.piece_board(Piece::Pawn, strong);
+
+    let file = file_of(pawns.get_first_square());
+    if (file != 0 && file != 7) || pawns.into_iter().any(|pawn| file_of(pawn) != file) {
+        return None;
+    }
+
+    let promotion = match strong {
+        Color::White => file,
+        Color::Black => 56 + file,
+    };
+
+    let bishops = board.piece_board(Piece::Bishop, strong);
+    if bishops
+        .into_iter()
+        .any(|bishop| is_light_square(bishop) == is_light_square(promotion))
+    {
+        return None;
+    }
+
+    match distance(king_square(board, strong.opposite()), promotion) <= 1 {
+        true => Some(DRAW),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(fen: &str) -> Option<Score> {
+        let board = Board::new(fen);
+        Endgame::find(&board)?.evaluate(&board, &MaterialParams::default())
+    }
+
+    #[test]
+    fn test_kpk() {
+        // king in front of its pawn wins no matter who moves, for either color
+        assert!(evaluate("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1").unwrap() > KNOWN_WIN);
+        assert!(evaluate("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1").unwrap() < -KNOWN_WIN);
+        assert!(evaluate("8/8/8/8/4p3/4k3/8/4K3 b - - 0 1").unwrap() > KNOWN_WIN);
+
+        // defending king in front of the pawn, and a rook pawn with the king in the corner
+        assert_eq!(evaluate("8/8/8/8/8/4k3/4P3/4K3 w - - 0 1"), Some(DRAW));
+        assert_eq!(evaluate("k7/8/8/8/P7/8/8/4K3 w - - 0 1"), Some(DRAW));
+
+        // pawn too far ahead for the defending king to catch
+        assert!(evaluate("8/8/8/1P6/8/8/6k1/K7 w - - 0 1").unwrap() > KNOWN_WIN);
+    }
+
+    #[test]
+    fn test_mop_up() {
+        // losing king is better off in the center than at the edge
+        let center = evaluate("8/8/8/3k4/8/8/8/R3K3 w - - 0 1").unwrap();
+        let edge = evaluate("3k4/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(center > KNOWN_WIN && edge > center);
+
+        // for bishop and knight, only the corners of the bishop's color matter
+        let right_corner = evaluate("7k/8/8/8/8/8/8/2B1KN2 w - - 0 1").unwrap();
+        let wrong_corner = evaluate("k7/8/8/8/8/8/8/2B1KN2 w - - 0 1").unwrap();
+        assert!(right_corner > wrong_corner);
+    }
+
+    #[test]
+    fn test_known_draws() {
+        assert_eq!(evaluate("8/8/3k4/8/8/8/8/2N1KN2 w - - 0 1"), Some(DRAW));
+        assert_eq!(evaluate("8/8/3k4/8/8/8/8/2B1K3 b - - 0 1"), Some(DRAW));
+
+        // a light-squared bishop can't help a rook pawn promote on a dark square
+        assert_eq!(evaluate("7k/8/8/7P/8/8/8/4KB2 w - - 0 1"), Some(DRAW));
+        assert_eq!(evaluate("7k/8/8/7P/8/8/8/2B1K3 w - - 0 1"), None);
+        assert_eq!(evaluate("8/8/8/3k3P/8/8/8/4KB2 w - - 0 1"), None);
+
+        // several pawns without a bishop are left to the regular evaluation
+        assert!(Endgame::find(&Board::new("k7/8/8/P7/P7/8/8/4K3 w - - 0 1")).is_none());
+    }
+}
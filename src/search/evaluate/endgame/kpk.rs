@@ -0,0 +1,197 @@
+use crate::{
+    board::{KING_MOVES, PAWN_ATTACKS},
+    core::{distance, file_of, rank_of, Bitboard, Color, Square, BOARD_SIZE},
+};
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref KPK_BITBASE: Vec<u64> = generate_bitbase();
+}
+
+// pawns are restricted to files a-d (the rest are mirrored onto them) and ranks 2-7
+const PAWN_FILES: usize = 4;
+const PAWN_RANKS: usize = 6;
+
+/// Total number of positions, indexed by side to move, strong king, weak king and pawn
+const SIZE: usize = 2 * BOARD_SIZE * BOARD_SIZE * PAWN_FILES * PAWN_RANKS;
+
+/// Classification of a single position while the bitbase is being generated
+#[derive(Clone, Copy, PartialEq)]
+enum Result {
+    Invalid,
+    Unknown,
+    Draw,
+    Win,
+}
+
+/// Returns true if the side with the pawn wins the king and pawn vs. king ending
+///
+/// Squares are given for a white pawn, black pawns must be mirrored vertically by the caller
+pub fn probe(strong_king: Square, pawn: Square, weak_king: Square, strong_to_move: bool) -> bool {
+    // the bitbase only stores pawns on the queenside, as the kingside is a mirror of it
+    let (strong_king, pawn, weak_king) = match file_of(pawn) < PAWN_FILES {
+        true => (strong_king, pawn, weak_king),
+        false => (strong_king ^ 7, pawn ^ 7, weak_king ^ 7),
+    };
+
+    let index = index(strong_to_move, strong_king, weak_king, pawn);
+    KPK_BITBASE[index / 64] & (1 << (index % 64)) != 0
+}
+
+/// Maps a position to its index within the bitbase
+fn index(strong_to_move: bool, strong_king: Square, weak_king: Square, pawn: Square) -> usize {
+    let pawn_index = file_of(pawn) * PAWN_RANKS + (rank_of(pawn) - 1);
+
+    let kings = (strong_to_move as usize * BOARD_SIZE + strong_king) * BOARD_SIZE + weak_king;
+
+    kings * PAWN_FILES * PAWN_RANKS + pawn_index
+}
+
+/// Maps an index within the bitbase back to its position
+fn position(index: usize) -> (bool, Square, Square, Square) {
+    let pawn_index = index % (PAWN_FILES * PAWN_RANKS);
+    let pawn_file = pawn_index / PAWN_RANKS;
+    let pawn_rank = pawn_index % PAWN_RANKS + 1;
+
+    let index = index / (PAWN_FILES * PAWN_RANKS);
+    let weak_king = index % BOARD_SIZE;
+    let strong_king = (index / BOARD_SIZE) % BOARD_SIZE;
+    let strong_to_move = index / (BOARD_SIZE * BOARD_SIZE) == 1;
+
+    let pawn = (7 - pawn_rank) * 8 + pawn_file;
+
+    (strong_to_move, strong_king, weak_king, pawn)
+}
+
+/// Generates the bitbase by repeatedly classifying positions from the results of their successors
+fn generate_bitbase() -> Vec<u64> {
+    let mut results = (0..SIZE).map(initial_result).collect::<Vec<_>>();
+
+    // keep going until no unknown positions can be resolved, any left over are draws by repetition
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for index in 0..SIZE {
+            if results[index] == Result::Unknown {
+                results[index] = classify(index, &results);
+                changed |= results[index] != Result::Unknown;
+            }
+        }
+    }
+
+    let mut bitbase = vec![0; SIZE.div_ceil(64)];
+
+    for (index, result) in results.into_iter().enumerate() {
+        if result == Result::Win {
+            bitbase[index / 64] |= 1 << (index % 64);
+        }
+    }
+
+    bitbase
+}
+
+/// Classifies positions that can be decided without looking at any successors
+fn initial_result(index: usize) -> Result {
+    let (strong_to_move, strong_king, weak_king, pawn) = position(index);
+
+    let pawn_attacks = PAWN_ATTACKS[Color::White][pawn];
+
+    if strong_king == weak_king
+        || strong_king == pawn
+        || weak_king == pawn
+        || distance(strong_king, weak_king) <= 1
+        || (strong_to_move && pawn_attacks.bit_at(weak_king))
+    {
+        return Result::Invalid;
+    }
+
+    if strong_to_move {
+        // the pawn promotes safely if the queen can't be taken, or is defended
+        let promotion = pawn - 8;
+
+        if rank_of(pawn) == 6
+            && strong_king != promotion
+            && weak_king != promotion
+            && (distance(weak_king, promotion) > 1 || distance(strong_king, promotion) == 1)
+        {
+            return Result::Win;
+        }
+    } else {
+        let weak_moves = weak_king_moves(strong_king, weak_king, pawn);
+
+        // a weak king without moves is either mated or stalemated
+        if weak_moves.is_empty() {
+            return match pawn_attacks.bit_at(weak_king) {
+                true => Result::Win,
+                false => Result::Draw,
+            };
+        }
+
+        // an undefended pawn next to the weak king is simply lost
+        if distance(weak_king, pawn) == 1 && distance(strong_king, pawn) > 1 {
+            return Result::Draw;
+        }
+    }
+
+    Result::Unknown
+}
+
+/// Classifies a position from the results of the positions it can move to
+fn classify(index: usize, results: &[Result]) -> Result {
+    let (strong_to_move, strong_king, weak_king, pawn) = position(index);
+
+    if strong_to_move {
+        let mut children = Vec::new();
+
+        for to in KING_MOVES[strong_king] & !KING_MOVES[weak_king] {
+            if to != pawn {
+                children.push(results[self::index(false, to, weak_king, pawn)]);
+            }
+        }
+
+        // pushes to the last rank are handled when the position is first classified
+        if rank_of(pawn) < 6 {
+            let single = pawn - 8;
+
+            if single != strong_king && single != weak_king {
+                children.push(results[self::index(false, strong_king, weak_king, single)]);
+
+                let double = pawn - 16;
+                if rank_of(pawn) == 1 && double != strong_king && double != weak_king {
+                    children.push(results[self::index(false, strong_king, weak_king, double)]);
+                }
+            }
+        }
+
+        // the strong side picks the best move
+        if children.contains(&Result::Win) {
+            Result::Win
+        } else if children.iter().all(|result| *result == Result::Draw) {
+            Result::Draw
+        } else {
+            Result::Unknown
+        }
+    } else {
+        let children = weak_king_moves(strong_king, weak_king, pawn)
+            .map(|to| results[self::index(true, strong_king, to, pawn)])
+            .collect::<Vec<_>>();
+
+        // the weak side holds the draw if any of its moves do
+        if children.contains(&Result::Draw) {
+            Result::Draw
+        } else if children.iter().all(|result| *result == Result::Win) {
+            Result::Win
+        } else {
+            Result::Unknown
+        }
+    }
+}
+
+/// Generates the squares the weak king can move to without capturing the pawn or moving into check
+fn weak_king_moves(strong_king: Square, weak_king: Square, pawn: Square) -> Bitboard {
+    KING_MOVES[weak_king]
+        & !KING_MOVES[strong_king]
+        & !PAWN_ATTACKS[Color::White][pawn]
+        & !Bitboard::shifted_board(pawn)
+}
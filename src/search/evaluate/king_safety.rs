@@ -0,0 +1,176 @@
+use super::{attacks::piece_attacks, masks::FILE_MASKS, params::eval_params};
+use crate::{
+    board::{Board, KING_MOVES},
+    core::{file_of, relative_rank, Bitboard, Color, Piece, Square},
+    search::{tapered::Tapered, Score},
+};
+
+/// Least amount of pieces attacking the king zone for the attack to be considered dangerous
+const MIN_ATTACKERS: usize = 2;
+
+eval_params! {
+    /// Weights of the king safety terms
+    pub struct KingSafetyParams {
+        /// Attack units added for each square of the king zone attacked by a piece, indexed by piece
+        attack_weights: [Score; 6] = [0, 2, 2, 3, 5, 0],
+
+        /// Penalty for attack units on the king zone, growing quickly as more pieces join the attack until it levels off
+        ///
+        /// Table is fetched from https://www.chessprogramming.org/King_Safety#Attack_Units
+        safety_table: [Score; 100] = [
+              0,   0,   1,   2,   3,   5,   7,   9,  12,  15,
+             18,  22,  26,  30,  35,  39,  44,  50,  56,  62,
+             68,  75,  82,  85,  89,  97, 105, 113, 122, 131,
+            140, 150, 169, 180, 191, 202, 213, 225, 237, 248,
+            260, 272, 283, 295, 307, 319, 330, 342, 354, 366,
+            377, 389, 401, 412, 424, 436, 448, 459, 471, 483,
+            494, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+            500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+            500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+            500, 500, 500, 500, 500, 500, 500, 500, 500, 500,
+        ],
+
+        /// Bonus for the closest friendly pawn in front of the king on each file near it, by the relative rank of the pawn
+        shelter: [Score; 8] = [0, 30, 20, 8, 2, 0, 0, 0],
+
+        /// Penalty for the closest enemy pawn advancing towards the king on each file near it, by its rank relative to the
+        /// king's side
+        storm: [Score; 8] = [0, 0, -40, -30, -15, -5, 0, 0],
+
+        /// Pawn storms are much slower when the enemy pawn is blocked by a friendly pawn
+        blocked_storm: [Score; 8] = [0, 0, -15, -5, 0, 0, 0, 0],
+
+        // penalties for files near the king without friendly pawns, which let enemy rooks and queens in
+        semi_open_file: Score = -15,
+        open_file: Score = -25,
+    }
+}
+
+/// Evaluates how safe the king of the given color is, shelter and attacks only matter while the enemy has a queen to
+/// lead an attack with
+pub fn evaluate_king_safety(board: &Board, color: Color, params: &KingSafetyParams) -> Tapered {
+    if board.piece_board(Piece::Queen, color.opposite()).is_empty() {
+        return Tapered::default();
+    }
+
+    let king = board.piece_board(Piece::King, color).get_first_square();
+
+    let shelter = pawn_shelter(board, color, king, params);
+    let danger = params.safety_table[king_attack_units(board, color, king, params).min(99)];
+
+    Tapered::new(shelter.saturating_sub(danger), -(danger / 4))
+}
+
+/// Scores the pawns in front of the king, the pawns advancing towards it and the files open to attack on
+fn pawn_shelter(board: &Board, color: Color, king: Square, params: &KingSafetyParams) -> Score {
+    let own_pawns = board.piece_board(Piece::Pawn, color);
+    let enemy_pawns = board.piece_board(Piece::Pawn, color.opposite());
+
+    let king_rank = relative_rank(king, color);
+
+    // the king's file and the ones beside it, shifted inwards for kings on the edge files
+    let center_file = file_of(king).clamp(1, 6);
+
+    let mut score: Score = 0;
+
+    for file in center_file - 1..=center_file + 1 {
+        // only pawns level with or in front of the king shelter it or storm towards it
+        let pawns_ahead = |pawns: Bitboard| {
+            (pawns & FILE_MASKS[file])
+                .map(|pawn| relative_rank(pawn, color))
+                .filter(|rank| *rank >= king_rank)
+                .min()
+        };
+
+        let shelter_rank = pawns_ahead(own_pawns);
+        let storm_rank = pawns_ahead(enemy_pawns);
+
+        score = score.saturating_add(match shelter_rank {
+            Some(rank) => params.shelter[rank],
+            None if (enemy_pawns & FILE_MASKS[file]).is_empty() => params.open_file,
+            None => params.semi_open_file,
+        });
+
+        if let Some(rank) = storm_rank {
+            let blocked = shelter_rank == Some(rank - 1);
+
+            score = score.saturating_add(match blocked {
+                true => params.blocked_storm[rank],
+                false => params.storm[rank],
+            });
+        }
+    }
+
+    score
+}
+
+/// Counts the attack units of enemy pieces against the squares around the king
+fn king_attack_units(board: &Board, color: Color, king: Square, params: &KingSafetyParams) -> usize {
+    use Piece::*;
+
+    let zone = KING_MOVES[king] | Bitboard::shifted_board(king);
+    let occupancy = board.all_pieces();
+    let enemy = color.opposite();
+
+    let mut attackers = 0;
+    let mut units = 0;
+
+    for piece in [Knight, Bishop, Rook, Queen] {
+        for square in board.piece_board(piece, enemy) {
+            let attacked = piece_attacks(piece, enemy, square, occupancy) & zone;
+
+            if !attacked.is_empty() {
+                attackers += 1;
+                units += params.attack_weights[piece as usize].max(0) as usize * attacked.count_bits();
+            }
+        }
+    }
+
+    match attackers >= MIN_ATTACKERS {
+        true => units,
+        false => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn white_king_safety(fen: &str) -> Tapered {
+        evaluate_king_safety(&Board::new(fen), Color::White, &KingSafetyParams::default())
+    }
+
+    #[test]
+    fn test_pawn_shelter() {
+        // intact shelter is better than pushed pawns, which is better than an open king
+        let intact = white_king_safety("3qk3/8/8/8/8/8/5PPP/6K1 w - - 0 1");
+        let pushed = white_king_safety("3qk3/8/8/8/8/5PPP/8/6K1 w - - 0 1");
+        let open = white_king_safety("3qk3/8/8/8/8/8/8/6K1 w - - 0 1");
+        assert!(intact.mg > pushed.mg && pushed.mg > open.mg);
+
+        // enemy pawns storming towards the king are dangerous
+        let storm = white_king_safety("3qk3/8/8/8/6p1/8/5PPP/6K1 w - - 0 1");
+        assert!(storm.mg < intact.mg);
+    }
+
+    #[test]
+    fn test_king_attacks() {
+        // a single attacker isn't counted, but queen and knight together are
+        let knight = white_king_safety("3qk3/8/8/8/8/5n2/5PPP/6K1 w - - 0 1");
+        let queen_knight = white_king_safety("4k3/8/8/8/7q/5n2/5PPP/6K1 w - - 0 1");
+        let intact = white_king_safety("3qk3/8/8/8/8/8/5PPP/6K1 w - - 0 1");
+
+        assert_eq!(knight, intact);
+        assert!(queen_knight.mg < intact.mg - 20);
+    }
+
+    #[test]
+    fn test_no_enemy_queen() {
+        // without a queen neither the open king nor the rooks and knight around it are counted
+        let open = white_king_safety("4k3/8/8/8/8/8/8/6K1 w - - 0 1");
+        let attacked = white_king_safety("4k3/8/8/6r1/7r/5n2/8/6K1 w - - 0 1");
+
+        assert_eq!(open, Tapered::default());
+        assert_eq!(attacked, Tapered::default());
+    }
+}
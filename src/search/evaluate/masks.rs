@@ -0,0 +1,54 @@
+use crate::core::{file_of, rank_of, Bitboard, Color, BOARD_SIZE, NUM_COLORS};
+use lazy_static::lazy_static;
+
+type LookupTable = [Bitboard; BOARD_SIZE];
+
+lazy_static! {
+    /// Every square on each file, from the a-file to the h-file
+    pub static ref FILE_MASKS: [Bitboard; 8] = std::array::from_fn(|f| squares_where(|file, _| file == f));
+
+    /// Every square on each rank, from the first rank to the eighth rank
+    pub static ref RANK_MASKS: [Bitboard; 8] = std::array::from_fn(|r| squares_where(|_, rank| rank == r));
+
+    /// Every square on the files next to each file
+    pub static ref ADJACENT_FILE_MASKS: [Bitboard; 8] =
+        std::array::from_fn(|f| squares_where(|file, _| file.abs_diff(f) == 1));
+
+    /// Squares in front of a pawn on its own and adjacent files, an enemy pawn on any of them stops it from being passed
+    pub static ref PASSED_PAWN_MASKS: [LookupTable; NUM_COLORS] = [
+        generate_passed_pawn_masks(Color::White),
+        generate_passed_pawn_masks(Color::Black),
+    ];
+}
+
+/// Generates a bitboard of all squares matching the given condition
+fn squares_where(condition: impl Fn(usize, usize) -> bool) -> Bitboard {
+    let mut board = Bitboard::EMPTY;
+
+    for square in 0..BOARD_SIZE {
+        if condition(file_of(square), rank_of(square)) {
+            board.set_bit_at(square, true);
+        }
+    }
+
+    board
+}
+
+fn generate_passed_pawn_masks(color: Color) -> LookupTable {
+    let mut boards = [Bitboard::EMPTY; BOARD_SIZE];
+
+    for (square, board) in boards.iter_mut().enumerate() {
+        let (pawn_file, pawn_rank) = (file_of(square), rank_of(square));
+
+        *board = squares_where(|file, rank| {
+            let ahead = match color {
+                Color::White => rank > pawn_rank,
+                Color::Black => rank < pawn_rank,
+            };
+
+            ahead && file.abs_diff(pawn_file) <= 1
+        });
+    }
+
+    boards
+}
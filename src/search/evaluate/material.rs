@@ -0,0 +1,171 @@
+use super::{endgame::Endgame, params::eval_params};
+use crate::{
+    board::Board,
+    core::{Color, Piece, ALL_PIECES, NUM_PIECES},
+    search::{
+        tapered::{game_phase, Tapered},
+        tt::TranspositionTable,
+        Score,
+    },
+};
+
+/// Size of the material hash table in MB
+pub const MATERIAL_TABLE_SIZE: usize = 1;
+
+/// Hash table caching everything that only depends on the amount of each piece, keyed by the material zobrist hash
+pub type MaterialTable = TranspositionTable<MaterialEntry>;
+
+/// Amount of piece types counted for imbalances, the bishop pair is counted as an extra type in front of the pieces
+const IMBALANCE_TYPES: usize = 6;
+
+eval_params! {
+    /// Weights of the material on the board
+    pub struct MaterialParams {
+        /// Value of each piece, starting from the same fixed values used by the rest of the engine
+        values: [Tapered; NUM_PIECES] = ALL_PIECES.map(|piece| {
+            Tapered::new(piece.material_value(), piece.material_value())
+        }),
+
+        // quadratic imbalance terms, the value of each piece type changes with the amount of own pieces (ours) and
+        // enemy pieces (theirs) of every type up to and including its own, in the order: bishop pair, pawn, knight,
+        // bishop, rook, queen
+        //
+        // values are based on https://github.com/official-stockfish/Stockfish/blob/sf_11/src/material.cpp, with the
+        // bishop pair itself left out as it is already covered by the piece evaluation
+
+        quadratic_ours: [[Score; IMBALANCE_TYPES]; IMBALANCE_TYPES] = [
+            [    0,   0,   0,   0,    0,  0],
+            [   40,  38,   0,   0,    0,  0],
+            [   32, 255, -62,   0,    0,  0],
+            [    0, 104,   4,   0,    0,  0],
+            [  -26,  -2,  47, 105, -208,  0],
+            [ -189,  24, 117, 133, -134, -6],
+        ],
+
+        quadratic_theirs: [[Score; IMBALANCE_TYPES]; IMBALANCE_TYPES] = [
+            [  0,   0,   0,   0,   0, 0],
+            [ 36,   0,   0,   0,   0, 0],
+            [  9,  63,   0,   0,   0, 0],
+            [ 59,  65,  42,   0,   0, 0],
+            [ 46,  39,  24, -24,   0, 0],
+            [ 97, 100, -42, 137, 268, 0],
+        ],
+    }
+}
+
+/// Scales the summed imbalance terms down to otter's material values
+const IMBALANCE_DIVISOR: i32 = 20;
+
+/// Cached evaluation of the material on the board
+#[derive(Clone, Copy, Default)]
+pub struct MaterialEntry {
+    pub imbalance: Tapered,       // from white's point of view
+    pub phase: i32,               // game phase, as it only depends on the pieces left
+    pub endgame: Option<Endgame>, // specialised evaluation for this material, if there is one
+}
+
+/// Fetches the material evaluation from the table, evaluating and storing it if it isn't there yet
+pub fn probe(board: &Board, table: &mut MaterialTable, params: &MaterialParams) -> MaterialEntry {
+    let key = board.material_zobrist();
+
+    if let Some(entry) = table.get(key) {
+        return entry;
+    }
+
+    let entry = MaterialEntry {
+        imbalance: imbalance(board, params),
+        phase: game_phase(board),
+        endgame: Endgame::find(board),
+    };
+    table.insert(key, entry);
+
+    entry
+}
+
+/// Evaluates how well the pieces of each side work together, beyond their fixed material values
+fn imbalance(board: &Board, params: &MaterialParams) -> Tapered {
+    let counts = [Color::White, Color::Black].map(|color| piece_counts(board, color));
+
+    let (white, black) = (&counts[Color::White as usize], &counts[Color::Black as usize]);
+    let bonus = color_imbalance(white, black, params) - color_imbalance(black, white, params);
+
+    let score = (bonus / IMBALANCE_DIVISOR).clamp(Score::MIN as i32, Score::MAX as i32) as Score;
+    Tapered::new(score, score)
+}
+
+/// Sums the quadratic imbalance terms for one side
+fn color_imbalance(
+    ours: &[i32; IMBALANCE_TYPES],
+    theirs: &[i32; IMBALANCE_TYPES],
+    params: &MaterialParams,
+) -> i32 {
+    let mut bonus = 0;
+
+    for first in 0..IMBALANCE_TYPES {
+        if ours[first] == 0 {
+            continue;
+        }
+
+        let value: i32 = (0..=first)
+            .map(|second| {
+                params.quadratic_ours[first][second] as i32 * ours[second]
+                    + params.quadratic_theirs[first][second] as i32 * theirs[second]
+            })
+            .sum();
+
+        bonus += ours[first] * value;
+    }
+
+    bonus
+}
+
+/// Amount of each piece type of the given color, in the order used by the imbalance tables
+fn piece_counts(board: &Board, color: Color) -> [i32; IMBALANCE_TYPES] {
+    use Piece::*;
+
+    let count = |piece: Piece| board.piece_board(piece, color).count_bits() as i32;
+
+    [
+        (count(Bishop) >= 2) as i32,
+        count(Pawn),
+        count(Knight),
+        count(Bishop),
+        count(Rook),
+        count(Queen),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_imbalance() {
+        let params = MaterialParams::default();
+
+        // identical material is balanced
+        assert_eq!(imbalance(&Board::default(), &params), Tapered::default());
+
+        // two minor pieces work better together than a rook and pawn with many pawns on the board
+        let minors = imbalance(
+            &Board::new("4k2r/pppppppp/8/8/8/8/1PPPPPPP/2B1KN2 w - - 0 1"),
+            &params,
+        );
+        assert!(minors.mg > 0);
+    }
+
+    #[test]
+    fn test_material_table() {
+        let mut table = MaterialTable::new(1);
+        let params = MaterialParams::default();
+
+        // the same material with pieces in different places shares a single entry
+        let a = probe(&Board::new("4k3/8/8/8/8/8/8/R3K2N w - - 0 1"), &mut table, &params);
+        let b = probe(&Board::new("4k3/8/2N5/8/8/3R4/8/4K3 b - - 0 1"), &mut table, &params);
+        assert_eq!(a.imbalance, b.imbalance);
+        assert_eq!(a.phase, 3);
+        assert!(a.endgame.is_some());
+
+        assert!(probe(&Board::default(), &mut table, &params).endgame.is_none());
+    }
+}
@@ -0,0 +1,157 @@
+use super::{
+    attacks::{piece_attacks, AttackMaps},
+    params::eval_params,
+};
+use crate::{
+    board::Board,
+    core::{Color, Piece},
+    search::tapered::Tapered,
+};
+
+// bonuses by the number of safe squares each piece attacks, pieces with very few squares are nearly trapped
+//
+// values are based on https://github.com/official-stockfish/Stockfish/blob/sf_11/src/evaluate.cpp, scaled to otter's material values
+
+eval_params! {
+    /// Mobility weights of each piece, indexed by the amount of safe squares it attacks
+    pub struct MobilityParams {
+        knight: [Tapered; 9] = [
+            Tapered::new(-31, -40),
+            Tapered::new(-26, -28),
+            Tapered::new(-6, -16),
+            Tapered::new(-2, -8),
+            Tapered::new(2, 4),
+            Tapered::new(6, 6),
+            Tapered::new(10, 8),
+            Tapered::new(14, 10),
+            Tapered::new(18, 13),
+        ],
+
+        bishop: [Tapered; 14] = [
+            Tapered::new(-24, -30),
+            Tapered::new(-10, -12),
+            Tapered::new(7, -4),
+            Tapered::new(14, 6),
+            Tapered::new(20, 10),
+            Tapered::new(26, 20),
+            Tapered::new(26, 28),
+            Tapered::new(30, 29),
+            Tapered::new(31, 32),
+            Tapered::new(34, 36),
+            Tapered::new(39, 39),
+            Tapered::new(42, 44),
+            Tapered::new(46, 44),
+            Tapered::new(48, 49),
+        ],
+
+        rook: [Tapered; 15] = [
+            Tapered::new(-30, -41),
+            Tapered::new(-12, -8),
+            Tapered::new(0, 8),
+            Tapered::new(2, 22),
+            Tapered::new(2, 36),
+            Tapered::new(7, 50),
+            Tapered::new(10, 51),
+            Tapered::new(15, 61),
+            Tapered::new(20, 66),
+            Tapered::new(20, 70),
+            Tapered::new(20, 76),
+            Tapered::new(22, 80),
+            Tapered::new(28, 82),
+            Tapered::new(29, 85),
+            Tapered::new(34, 88),
+        ],
+
+        queen: [Tapered; 28] = [
+            Tapered::new(-14, -24),
+            Tapered::new(-8, -14),
+            Tapered::new(-4, -4),
+            Tapered::new(-4, 8),
+            Tapered::new(9, 20),
+            Tapered::new(12, 27),
+            Tapered::new(12, 30),
+            Tapered::new(18, 36),
+            Tapered::new(20, 38),
+            Tapered::new(27, 48),
+            Tapered::new(32, 48),
+            Tapered::new(34, 50),
+            Tapered::new(34, 62),
+            Tapered::new(35, 62),
+            Tapered::new(35, 64),
+            Tapered::new(35, 65),
+            Tapered::new(36, 66),
+            Tapered::new(38, 66),
+            Tapered::new(38, 68),
+            Tapered::new(40, 70),
+            Tapered::new(40, 74),
+            Tapered::new(44, 74),
+            Tapered::new(44, 76),
+            Tapered::new(44, 84),
+            Tapered::new(48, 86),
+            Tapered::new(48, 88),
+            Tapered::new(48, 89),
+            Tapered::new(48, 92),
+        ],
+    }
+}
+
+/// Evaluates how many safe squares the pieces of the given color can move to
+///
+/// Squares occupied by friendly pieces or attacked by enemy pawns aren't counted, as pieces can't safely go there
+pub fn evaluate_mobility(
+    board: &Board,
+    color: Color,
+    attacks: &AttackMaps,
+    params: &MobilityParams,
+) -> Tapered {
+    use Piece::*;
+
+    let occupancy = board.all_pieces();
+    let safe_squares = !board.color_board(color) & !attacks.by_piece(Pawn, color.opposite());
+
+    let mut score = Tapered::default();
+
+    for piece in [Knight, Bishop, Rook, Queen] {
+        let table: &[Tapered] = match piece {
+            Knight => &params.knight,
+            Bishop => &params.bishop,
+            Rook => &params.rook,
+            _ => &params.queen,
+        };
+
+        for square in board.piece_board(piece, color) {
+            let mobility = piece_attacks(piece, color, square, occupancy) & safe_squares;
+            score += table[mobility.count_bits()];
+        }
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn white_mobility(fen: &str) -> Tapered {
+        let board = Board::new(fen);
+        evaluate_mobility(
+            &board,
+            Color::White,
+            &AttackMaps::new(&board),
+            &MobilityParams::default(),
+        )
+    }
+
+    #[test]
+    fn test_mobility() {
+        // a rook on an open file is better than one stuck behind its own pawn
+        let open = white_mobility("4k3/8/8/8/8/8/1P6/R3K3 w - - 0 1");
+        let closed = white_mobility("4k3/8/8/8/8/8/P7/R3K3 w - - 0 1");
+        assert!(open.mg > closed.mg && open.eg > closed.eg);
+
+        // squares covered by enemy pawns aren't safe for a knight
+        let free = white_mobility("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1");
+        let covered = white_mobility("4k3/3p4/6p1/8/3N4/8/8/4K3 w - - 0 1");
+        assert!(free.mg > covered.mg);
+    }
+}
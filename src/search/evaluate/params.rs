@@ -0,0 +1,347 @@
+use super::{
+    king_safety::KingSafetyParams, material::MaterialParams, mobility::MobilityParams,
+    passed::PassedParams, pawns::PawnParams, pieces::PieceParams, threats::ThreatParams,
+};
+use crate::search::{pst::PstParams, tapered::Tapered, Score};
+use std::{
+    fmt::Display,
+    fs,
+    io::{self, ErrorKind},
+};
+
+/// Weight of the evaluation that can be adjusted by tuning, made up of one or more plain scores
+pub trait Tunable {
+    /// Every score making up the weight, in a fixed order
+    fn values_mut(&mut self) -> Vec<&mut Score>;
+}
+
+impl Tunable for Score {
+    fn values_mut(&mut self) -> Vec<&mut Score> {
+        vec![self]
+    }
+}
+
+impl Tunable for Tapered {
+    fn values_mut(&mut self) -> Vec<&mut Score> {
+        vec![&mut self.mg, &mut self.eg]
+    }
+}
+
+impl<T: Tunable, const N: usize> Tunable for [T; N] {
+    fn values_mut(&mut self) -> Vec<&mut Score> {
+        self.iter_mut().flat_map(T::values_mut).collect()
+    }
+}
+
+/// Named group of weights used by a single part of the evaluation
+pub trait ParamGroup {
+    /// Every weight of the group along with its name, in the order they are declared
+    fn weights_mut(&mut self) -> Vec<(&'static str, &mut dyn Tunable)>;
+}
+
+/// Declares a group of evaluation weights, generating the struct, its default values and access to its weights
+macro_rules! eval_params {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $field:ident: $field_type:ty = $default:expr,
+            )*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, PartialEq, Debug)]
+        pub struct $name {
+            $(
+                $(#[$field_meta])*
+                pub $field: $field_type,
+            )*
+        }
+
+        impl Default for $name {
+            fn default() -> $name {
+                $name {
+                    $($field: $default,)*
+                }
+            }
+        }
+
+        impl $crate::search::evaluate::params::ParamGroup for $name {
+            fn weights_mut(
+                &mut self,
+            ) -> Vec<(&'static str, &mut dyn $crate::search::evaluate::params::Tunable)> {
+                vec![$((stringify!($field), &mut self.$field as _),)*]
+            }
+        }
+    };
+}
+
+pub(crate) use eval_params;
+
+/// Every weight used by the evaluation, defaulting to the values compiled into the engine
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct EvalParams {
+    pub material: MaterialParams,
+    pub pst: PstParams,
+    pub pawns: PawnParams,
+    pub passed: PassedParams,
+    pub king_safety: KingSafetyParams,
+    pub mobility: MobilityParams,
+    pub pieces: PieceParams,
+    pub threats: ThreatParams,
+}
+
+impl EvalParams {
+    /// Every group of weights along with its name
+    fn groups_mut(&mut self) -> Vec<(&'static str, &mut dyn ParamGroup)> {
+        vec![
+            ("material", &mut self.material),
+            ("pst", &mut self.pst),
+            ("pawns", &mut self.pawns),
+            ("passed", &mut self.passed),
+            ("king_safety", &mut self.king_safety),
+            ("mobility", &mut self.mobility),
+            ("pieces", &mut self.pieces),
+            ("threats", &mut self.threats),
+        ]
+    }
+
+    /// Loads weights from a file written in the format of `parse`
+    pub fn load(path: &str) -> io::Result<EvalParams> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+
+    /// Parses weights from a TOML document with a `[group]` table for each group, holding its weights as arrays of
+    /// whole numbers in the form `name = [values]`, as they are displayed
+    ///
+    /// Any TOML giving the same tables is accepted, such as dotted keys, inline tables and arrays spread over several
+    /// lines or nested into rows, which are read row by row. A weight with a single value can be a plain number too.
+    ///
+    /// Weights that aren't given keep their default values, so only the weights that change need to be written. Any
+    /// value that fits in a `Score` is accepted, the evaluation saturates instead of overflowing when adding up large
+    /// weights.
+    pub fn parse(text: &str) -> Result<EvalParams, String> {
+        let table = text.parse::<toml::Table>().map_err(|e| e.to_string())?;
+        let mut params = EvalParams::default();
+        let mut groups = params.groups_mut();
+
+        for (group_name, weights) in table.iter() {
+            let toml::Value::Table(weights) = weights else {
+                return Err(format!("`{}` is given outside of any [group]", group_name));
+            };
+
+            let Some((_, group)) = groups.iter_mut().find(|(name, _)| name == group_name) else {
+                return Err(format!("unknown group [{}]", group_name));
+            };
+            let mut group_weights = group.weights_mut();
+
+            for (name, value) in weights.iter() {
+                let Some((_, weight)) = group_weights
+                    .iter_mut()
+                    .find(|(weight_name, _)| weight_name == name)
+                else {
+                    return Err(format!("unknown weight `{}` in [{}]", name, group_name));
+                };
+
+                let mut values = Vec::new();
+                read_values(name, value, &mut values)?;
+
+                let mut weight = weight.values_mut();
+
+                if weight.len() != values.len() {
+                    return Err(format!(
+                        "`{}` has {} values, but {} are needed",
+                        name,
+                        values.len(),
+                        weight.len()
+                    ));
+                }
+
+                for (weight_value, value) in weight.iter_mut().zip(values) {
+                    **weight_value = value;
+                }
+            }
+        }
+
+        Ok(params)
+    }
+
+    /// Flattens every weight into a single parameter vector of mutable scores
+    pub fn values_mut(&mut self) -> Vec<&mut Score> {
+        self.groups_mut()
+            .into_iter()
+            .flat_map(|(_, group)| group.weights_mut())
+            .flat_map(|(_, weight)| weight.values_mut())
+            .collect()
+    }
+}
+
+/// Reads the whole numbers making up the value of a weight into a list, going through nested arrays in order
+fn read_values(name: &str, value: &toml::Value, values: &mut Vec<Score>) -> Result<(), String> {
+    match value {
+        toml::Value::Array(array) => array
+            .iter()
+            .try_for_each(|value| read_values(name, value, values)),
+
+        value => {
+            let score = value
+                .as_integer()
+                .and_then(|value| Score::try_from(value).ok())
+                .ok_or_else(|| {
+                    format!(
+                        "value `{}` of `{}` is not a whole number from {} to {}",
+                        value,
+                        name,
+                        Score::MIN,
+                        Score::MAX
+                    )
+                })?;
+
+            values.push(score);
+            Ok(())
+        }
+    }
+}
+
+impl Display for EvalParams {
+    /// Writes every weight in the form `name = [values]`, under a `[group]` header for each group of weights
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut params = self.clone();
+
+        for (group_name, group) in params.groups_mut() {
+            writeln!(f, "[{}]", group_name)?;
+
+            for (name, weight) in group.weights_mut() {
+                let values = weight
+                    .values_mut()
+                    .into_iter()
+                    .map(|value| value.to_string())
+                    .collect::<Vec<_>>();
+
+                writeln!(f, "{} = [{}]", name, values.join(", "))?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_vector(params: &EvalParams) -> Vec<Score> {
+        params
+            .clone()
+            .values_mut()
+            .into_iter()
+            .map(|value| *value)
+            .collect()
+    }
+
+    #[test]
+    fn test_parameter_vector() {
+        let mut params = EvalParams::default();
+        let vector = to_vector(&params);
+
+        // changing a value through the vector changes the weight it belongs to
+        *params.values_mut()[0] += 5;
+        assert_ne!(params, EvalParams::default());
+        assert_eq!(to_vector(&params)[0], vector[0] + 5);
+        assert_eq!(to_vector(&params)[1..], vector[1..]);
+
+        // every value is written out exactly once
+        let written = params.to_string();
+        let count = written
+            .lines()
+            .filter_map(|line| line.split_once(" = "))
+            .map(|(_, values)| values.split(", ").count())
+            .sum::<usize>();
+        assert_eq!(count, vector.len());
+
+        // and reads back as the same weights
+        assert_eq!(EvalParams::parse(&written), Ok(params));
+    }
+
+    #[test]
+    fn test_parse_params() {
+        // weights that aren't given keep their defaults
+        let params =
+            EvalParams::parse("# only bishop pairs\n[pieces]\nbishop_pair = [10, 20]\n").unwrap();
+        assert_eq!(params.pieces.bishop_pair, Tapered::new(10, 20));
+        assert_eq!(params.pawns, EvalParams::default().pawns);
+
+        // other ways of writing the same tables in TOML give the same weights
+        for text in [
+            "[pieces]\nbishop_pair = [\n    10,\n    20, # endgame\n]",
+            "pieces.bishop_pair = [10, 20]",
+            "pieces = { bishop_pair = [10, 20] }",
+            "[pieces]\nbishop_pair = [[10], [20]]",
+        ] {
+            assert_eq!(EvalParams::parse(text), Ok(params.clone()), "{}", text);
+        }
+
+        // invalid files point out the weight with the problem
+        for (text, error) in [
+            (
+                "[pieces]\nbishop_pair = [10]",
+                "`bishop_pair` has 1 values, but 2 are needed",
+            ),
+            ("[piece]\nbishop_pair = [10, 20]", "unknown group [piece]"),
+            (
+                "bishop_pair = [10, 20]",
+                "`bishop_pair` is given outside of any [group]",
+            ),
+            (
+                "[pieces]\n\nbishop_par = [10, 20]",
+                "unknown weight `bishop_par` in [pieces]",
+            ),
+            (
+                "[pieces]\nbishop_pair = [10, 2.5]",
+                "value `2.5` of `bishop_pair` is not a whole number from -32768 to 32767",
+            ),
+            (
+                "[pieces]\nbishop_pair = [10, 40000]",
+                "value `40000` of `bishop_pair` is not a whole number from -32768 to 32767",
+            ),
+        ] {
+            assert_eq!(EvalParams::parse(text), Err(error.to_string()));
+        }
+
+        // as are files that aren't valid TOML, such as weights given twice
+        let error = EvalParams::parse("[pieces]\nbishop_pair = [1, 2]\nbishop_pair = [1, 2]");
+        assert!(error.unwrap_err().contains("line 3"));
+    }
+
+    #[test]
+    fn test_extreme_params() {
+        use crate::{
+            board::Board,
+            search::evaluate::{evaluate, EvalTables, MAX_EVAL},
+        };
+
+        // weights as large as a file can give them don't overflow the evaluation, or push it near mate scores
+        for extreme in [Score::MAX, Score::MIN] {
+            let mut params = EvalParams::default();
+            params
+                .values_mut()
+                .into_iter()
+                .for_each(|value| *value = extreme);
+
+            for fen in [
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+                "1k6/1pp5/8/P2P4/8/3qQ3/5PPP/6K1 w - - 0 1",
+                "8/8/8/3k4/8/8/8/R3K3 b - - 0 1",
+                "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1",
+            ] {
+                let score = evaluate(&Board::new(fen), &params, &mut EvalTables::uncached());
+                assert!(score.abs() <= MAX_EVAL, "{}", fen);
+            }
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use super::{
+    attacks::AttackMaps,
+    masks::{FILE_MASKS, PASSED_PAWN_MASKS},
+    params::eval_params,
+    pawns::stop_square,
+};
+use crate::{
+    board::Board,
+    core::{distance, file_of, relative_rank, Bitboard, Color, Piece, Square},
+    search::{tapered::Tapered, Score},
+};
+
+eval_params! {
+    /// Weights of the passed pawn terms that depend on more than the pawns
+    pub struct PassedParams {
+        /// Scales the passed pawn terms below by relative rank, pawns far from promotion barely matter
+        rank_weights: [Score; 8] = [0, 0, 0, 1, 3, 5, 7, 0],
+
+        // king distances to the square in front of the pawn, the defending king is the more important one
+        enemy_king_distance: Score = 5,
+        own_king_distance: Score = 2,
+
+        /// Penalty for an enemy piece blockading the pawn
+        blocked: Tapered = Tapered::new(-2, -4),
+
+        /// Bonus when no piece stands in the way of the pawn and no enemy piece controls its path
+        free_path: Tapered = Tapered::new(4, 10),
+
+        /// Bonus when the path is free of pieces, but only the next square is safe to advance to
+        safe_stop: Tapered = Tapered::new(2, 5),
+
+        /// Bonus for a pawn in a pawn ending that the defending king can no longer catch
+        unstoppable: Tapered = Tapered::new(0, 500),
+    }
+}
+
+/// Evaluates the passed pawns of the given color, using terms that depend on the pieces around them
+pub fn evaluate_passed_pawns(
+    board: &Board,
+    color: Color,
+    passed: Bitboard,
+    attacks: &AttackMaps,
+    params: &PassedParams,
+) -> Tapered {
+    let enemy = color.opposite();
+
+    let own_king = board.piece_board(Piece::King, color).get_first_square();
+    let enemy_king = board.piece_board(Piece::King, enemy).get_first_square();
+
+    // the defending side can only stop pawns with its king once it has no pieces left
+    let pawn_ending = (board.color_board(enemy)
+        & !board.piece_board(Piece::Pawn, enemy)
+        & !board.piece_board(Piece::King, enemy))
+    .is_empty();
+
+    let mut score = Tapered::default();
+
+    for square in passed & board.piece_board(Piece::Pawn, color) {
+        let rank = relative_rank(square, color);
+        let weight = params.rank_weights[rank];
+        let stop = stop_square(square, color);
+
+        // squares the pawn still has to pass through to promote
+        let path = PASSED_PAWN_MASKS[color][square] & FILE_MASKS[file_of(square)];
+
+        // kings close to the pawn's path can support or stop it
+        let enemy_proximity = params.enemy_king_distance.saturating_mul(distance(enemy_king, stop).min(5) as Score);
+        let own_proximity = params.own_king_distance.saturating_mul(distance(own_king, stop).min(5) as Score);
+        let king_proximity = enemy_proximity.saturating_sub(own_proximity);
+        score += Tapered::new(0, weight.saturating_mul(king_proximity));
+
+        if board.color_board(enemy).bit_at(stop) {
+            score += params.blocked * weight;
+        } else if (path & board.all_pieces()).is_empty() {
+            if (path & attacks.all(enemy)).is_empty() {
+                score += params.free_path * weight;
+            } else if !attacks.all(enemy).bit_at(stop) {
+                score += params.safe_stop * weight;
+            }
+
+            if pawn_ending && outside_square(board, color, square, enemy_king) {
+                score += params.unstoppable;
+            }
+        }
+    }
+
+    score
+}
+
+/// Rule of the square: returns true if the defending king is too far away to catch the pawn before it promotes
+fn outside_square(board: &Board, color: Color, pawn: Square, enemy_king: Square) -> bool {
+    let rank = relative_rank(pawn, color);
+
+    let promotion = match color {
+        Color::White => file_of(pawn),
+        Color::Black => 56 + file_of(pawn),
+    };
+
+    // pawns on their starting rank can move two squares at once
+    let pawn_moves = 7 - rank - (rank == 1) as usize;
+
+    // the defending king gets a tempo if it is its turn
+    let king_moves = distance(enemy_king, promotion) - (board.active_color() != color) as usize;
+
+    pawn_moves < king_moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn white_passers(fen: &str) -> Tapered {
+        let board = Board::new(fen);
+        let passed = board.piece_board(Piece::Pawn, Color::White);
+
+        evaluate_passed_pawns(
+            &board,
+            Color::White,
+            passed,
+            &AttackMaps::new(&board),
+            &PassedParams::default(),
+        )
+    }
+
+    #[test]
+    fn test_king_distance() {
+        // an escorting king is better than a distant one, and a distant defender is better than a close one
+        let escorted = white_passers("8/1k6/8/4P3/4K3/8/8/8 w - - 0 1");
+        let alone = white_passers("8/1k6/8/4P3/8/8/8/K7 w - - 0 1");
+        let defended = white_passers("8/4k3/8/4P3/4K3/8/8/8 w - - 0 1");
+        assert!(escorted.eg > alone.eg && escorted.eg > defended.eg);
+    }
+
+    #[test]
+    fn test_blockers() {
+        let free = white_passers("7k/8/8/3P4/8/8/8/4K3 w - - 0 1");
+        let blocked = white_passers("1n5k/8/8/3P4/8/8/8/4K3 w - - 0 1");
+        let blockaded = white_passers("7k/8/3n4/3P4/8/8/8/4K3 w - - 0 1");
+        assert!(free.eg > blocked.eg && blocked.eg > blockaded.eg);
+    }
+
+    #[test]
+    fn test_rule_of_the_square() {
+        // the black king can reach the promotion square in time, but only when it is black's turn
+        let unstoppable_bonus = PassedParams::default().unstoppable;
+
+        let caught = white_passers("8/8/8/2P5/6k1/8/8/K7 b - - 0 1");
+        let unstoppable = white_passers("8/8/8/2P5/6k1/8/8/K7 w - - 0 1");
+        assert!(unstoppable.eg - caught.eg >= unstoppable_bonus.eg);
+
+        // pieces can still catch the pawn
+        let knight = white_passers("8/8/8/2P5/6k1/8/7n/K7 w - - 0 1");
+        assert!(knight.eg < unstoppable_bonus.eg);
+    }
+}
@@ -0,0 +1,226 @@
+use super::{
+    masks::{ADJACENT_FILE_MASKS, FILE_MASKS, PASSED_PAWN_MASKS, RANK_MASKS},
+    params::eval_params,
+};
+use crate::{
+    board::{Board, KING_MOVES, PAWN_ATTACKS},
+    core::{file_of, rank_of, relative_rank, Bitboard, Color, Piece, Square},
+    search::{tapered::Tapered, tt::TranspositionTable, Score},
+};
+
+/// Size of the pawn hash table in MB
+pub const PAWN_TABLE_SIZE: usize = 4;
+
+/// Hash table caching pawn structure evaluations, keyed by the pawn-only zobrist hash of the board
+pub type PawnTable = TranspositionTable<PawnEntry>;
+
+eval_params! {
+    /// Weights of the pawn structure terms
+    pub struct PawnParams {
+        // penalties for weak pawns
+        doubled: Tapered = Tapered::new(-10, -25),
+        isolated: Tapered = Tapered::new(-10, -15),
+        backward: Tapered = Tapered::new(-8, -12),
+
+        /// Bonus for a pawn that is defended by or standing beside another pawn, by its relative rank
+        connected: [Tapered; 8] = [
+            Tapered::new(0, 0),
+            Tapered::new(3, 2),
+            Tapered::new(5, 4),
+            Tapered::new(8, 6),
+            Tapered::new(15, 12),
+            Tapered::new(25, 20),
+            Tapered::new(40, 35),
+            Tapered::new(0, 0),
+        ],
+
+        /// Extra bonus for pawns side by side, which can control both squares in front of each other
+        phalanx: Tapered = Tapered::new(5, 3),
+
+        /// Extra bonus for each pawn defending this one as part of a chain
+        chain: Tapered = Tapered::new(5, 5),
+
+        /// Bonus for a passed pawn by its relative rank, which grows much larger in the endgame when fewer pieces can
+        /// stop it
+        ///
+        /// Bonuses for passed pawns that depend on more than the pawns are found in `passed.rs`
+        passed: [Tapered; 8] = [
+            Tapered::new(0, 0),
+            Tapered::new(5, 10),
+            Tapered::new(5, 15),
+            Tapered::new(10, 25),
+            Tapered::new(20, 45),
+            Tapered::new(35, 75),
+            Tapered::new(60, 120),
+            Tapered::new(0, 0),
+        ],
+
+        /// Extra bonus for a passed pawn defended by another pawn, by its relative rank
+        protected_passer: [Tapered; 8] = [
+            Tapered::new(0, 0),
+            Tapered::new(0, 0),
+            Tapered::new(2, 5),
+            Tapered::new(5, 10),
+            Tapered::new(10, 20),
+            Tapered::new(15, 30),
+            Tapered::new(20, 40),
+            Tapered::new(0, 0),
+        ],
+
+        /// Extra bonus for a passed pawn with another passed pawn next to it, which are very hard to stop together
+        connected_passer: Tapered = Tapered::new(10, 25),
+    }
+}
+
+/// Cached evaluation of the pawn structure
+#[derive(Clone, Copy, Default)]
+pub struct PawnEntry {
+    pub score: Tapered,   // from white's point of view
+    pub passed: Bitboard, // passed pawns of both colors
+}
+
+/// Fetches the pawn structure evaluation from the table, evaluating and storing it if it isn't there yet
+pub fn probe(board: &Board, table: &mut PawnTable, params: &PawnParams) -> PawnEntry {
+    let key = board.pawn_zobrist();
+
+    if let Some(entry) = table.get(key) {
+        return entry;
+    }
+
+    let entry = evaluate_pawns(board, params);
+    table.insert(key, entry);
+
+    entry
+}
+
+/// Evaluates the pawn structure of both sides
+fn evaluate_pawns(board: &Board, params: &PawnParams) -> PawnEntry {
+    let (white_score, white_passed) = evaluate_color(board, Color::White, params);
+    let (black_score, black_passed) = evaluate_color(board, Color::Black, params);
+
+    PawnEntry {
+        score: white_score - black_score,
+        passed: white_passed | black_passed,
+    }
+}
+
+/// Evaluates the pawn structure of a single side, returning its score and its passed pawns
+pub fn evaluate_color(board: &Board, color: Color, params: &PawnParams) -> (Tapered, Bitboard) {
+    let own_pawns = board.piece_board(Piece::Pawn, color);
+    let enemy_pawns = board.piece_board(Piece::Pawn, color.opposite());
+
+    let mut score = Tapered::default();
+    let mut passed = Bitboard::EMPTY;
+
+    for square in own_pawns {
+        let file = file_of(square);
+        let rank = relative_rank(square, color);
+
+        // squares in front of the pawn on its own and adjacent files
+        let front_span = PASSED_PAWN_MASKS[color][square];
+
+        let neighbours = ADJACENT_FILE_MASKS[file] & own_pawns;
+        let supporters = PAWN_ATTACKS[color.opposite()][square] & own_pawns;
+        let phalanx = neighbours & RANK_MASKS[rank_of(square)];
+
+        // only the rearmost pawns of a doubled file are penalized
+        let doubled = !(front_span & FILE_MASKS[file] & own_pawns).is_empty();
+        let isolated = neighbours.is_empty();
+
+        // no neighbours level with or behind it can ever defend it, and it can't safely advance to get defended
+        let backward = !isolated
+            && (neighbours & !front_span).is_empty()
+            && !(PAWN_ATTACKS[color][stop_square(square, color)] & enemy_pawns).is_empty();
+
+        if doubled {
+            score += params.doubled;
+        }
+
+        if isolated {
+            score += params.isolated;
+        } else if backward {
+            score += params.backward;
+        }
+
+        if !supporters.is_empty() || !phalanx.is_empty() {
+            score += params.connected[rank];
+        }
+
+        if !phalanx.is_empty() {
+            score += params.phalanx;
+        }
+
+        score += params.chain * supporters.count_bits() as Score;
+
+        if !doubled && (front_span & enemy_pawns).is_empty() {
+            passed.set_bit_at(square, true);
+        }
+    }
+
+    for square in passed {
+        let rank = relative_rank(square, color);
+
+        score += params.passed[rank];
+
+        if !(PAWN_ATTACKS[color.opposite()][square] & own_pawns).is_empty() {
+            score += params.protected_passer[rank];
+        }
+
+        // passed pawns beside or diagonal to this one
+        if !(KING_MOVES[square] & ADJACENT_FILE_MASKS[file_of(square)] & passed).is_empty() {
+            score += params.connected_passer;
+        }
+    }
+
+    (score, passed)
+}
+
+/// Square directly in front of a pawn
+pub fn stop_square(square: Square, color: Color) -> Square {
+    match color {
+        Color::White => square - 8,
+        Color::Black => square + 8,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn white_structure(fen: &str) -> Tapered {
+        evaluate_color(&Board::new(fen), Color::White, &PawnParams::default()).0
+    }
+
+    #[test]
+    fn test_pawn_structure() {
+        // a pawn chain is better than two isolated pawns
+        let chain = white_structure("4k3/8/8/8/8/3P4/2P5/4K3 w - - 0 1");
+        let isolated = white_structure("4k3/8/8/8/8/3P4/1P6/4K3 w - - 0 1");
+        assert!(chain.mg > isolated.mg && chain.eg > isolated.eg);
+
+        // doubled pawns are worse than a phalanx
+        let doubled = white_structure("4k3/8/8/8/8/3P4/3P4/4K3 w - - 0 1");
+        let phalanx = white_structure("4k3/8/8/8/8/8/3PP3/4K3 w - - 0 1");
+        assert!(doubled.mg < phalanx.mg && doubled.eg < phalanx.eg);
+
+        // the e3 pawn is backward when its advance is covered by an enemy pawn
+        let backward = white_structure("4k3/8/8/5p2/3P4/4P3/8/4K3 w - - 0 1");
+        let not_backward = white_structure("4k3/8/5p2/8/3P4/4P3/8/4K3 w - - 0 1");
+        assert_eq!(backward - not_backward, PawnParams::default().backward);
+    }
+
+    #[test]
+    fn test_pawn_table() {
+        let mut table = PawnTable::new(1);
+        let params = PawnParams::default();
+        let board = Board::new("4k3/p5p1/8/2P5/8/8/PP4P1/4K3 w - - 0 1");
+
+        let entry = probe(&board, &mut table, &params);
+        let cached = probe(&board, &mut table, &params);
+        assert_eq!(entry.score, cached.score);
+        assert_eq!(entry.score, evaluate_pawns(&board, &params).score);
+
+        // only the c5 pawn is passed
+        assert!(entry.passed == Bitboard::shifted_board(26));
+    }
+}
@@ -0,0 +1,231 @@
+use super::{
+    attacks::{piece_attacks, AttackMaps},
+    masks::{ADJACENT_FILE_MASKS, FILE_MASKS, PASSED_PAWN_MASKS, RANK_MASKS},
+    params::eval_params,
+    pawns::stop_square,
+};
+use crate::{
+    board::Board,
+    core::{file_of, is_light_square, rank_of, relative_rank, Color, Piece, Square},
+    search::{tapered::Tapered, Score},
+};
+
+eval_params! {
+    /// Weights of the positional terms for minor pieces and rooks
+    pub struct PieceParams {
+        /// Bonus for having both bishops, which together can reach every square
+        bishop_pair: Tapered = Tapered::new(30, 50),
+
+        // rooks are strongest on files without friendly pawns, and on the seventh rank where they attack pawns and
+        // restrict the king
+        rook_open_file: Tapered = Tapered::new(25, 10),
+        rook_semi_open_file: Tapered = Tapered::new(12, 6),
+        rook_on_seventh: Tapered = Tapered::new(10, 25),
+
+        /// Bonus for minor pieces on squares in enemy territory defended by a pawn, which enemy pawns can never attack
+        knight_outpost: Tapered = Tapered::new(25, 15),
+        bishop_outpost: Tapered = Tapered::new(12, 6),
+
+        // bishops are restricted by their own pawns on the same colored squares, especially pawns that can't move
+        bad_bishop: Tapered = Tapered::new(-2, -4),
+        blocked_bad_bishop: Tapered = Tapered::new(-4, -4),
+
+        /// Penalty for a bishop on a7/h7 (or a2/h2 for black) that enemy pawns have cut off from the rest of the board
+        trapped_bishop: Tapered = Tapered::new(-100, -80),
+
+        /// Penalty for a rook stuck in the corner behind a king that can no longer castle
+        trapped_rook: Tapered = Tapered::new(-50, -10),
+    }
+}
+
+/// Most squares a rook can move to and still be considered trapped
+const TRAPPED_ROOK_MOBILITY: usize = 3;
+
+/// Evaluates the placement of the minor pieces and rooks of the given color
+pub fn evaluate_pieces(
+    board: &Board,
+    color: Color,
+    attacks: &AttackMaps,
+    params: &PieceParams,
+) -> Tapered {
+    let mut score = Tapered::default();
+
+    if board.piece_board(Piece::Bishop, color).count_bits() >= 2 {
+        score += params.bishop_pair;
+    }
+
+    score + rooks(board, color, params) + minor_pieces(board, color, attacks, params)
+}
+
+/// Rewards rooks on open files and the seventh rank, and penalizes rooks trapped by their own king
+fn rooks(board: &Board, color: Color, params: &PieceParams) -> Tapered {
+    let own_pawns = board.piece_board(Piece::Pawn, color);
+    let enemy_pawns = board.piece_board(Piece::Pawn, color.opposite());
+    let enemy_king = board
+        .piece_board(Piece::King, color.opposite())
+        .get_first_square();
+
+    let mut score = Tapered::default();
+
+    for square in board.piece_board(Piece::Rook, color) {
+        let file = FILE_MASKS[file_of(square)];
+
+        if (file & own_pawns).is_empty() {
+            score += match (file & enemy_pawns).is_empty() {
+                true => params.rook_open_file,
+                false => params.rook_semi_open_file,
+            };
+        }
+
+        // the seventh rank only matters if there are pawns to attack there or a king to cut off behind it
+        if relative_rank(square, color) == 6 {
+            let seventh_rank_pawns = RANK_MASKS[rank_of(square)] & enemy_pawns;
+
+            if !seventh_rank_pawns.is_empty() || relative_rank(enemy_king, color) == 7 {
+                score += params.rook_on_seventh;
+            }
+        }
+
+        if trapped_rook(board, color, square) {
+            score += params.trapped_rook;
+        }
+    }
+
+    score
+}
+
+/// Returns true if the rook is boxed into the corner by a king that has lost its castling rights
+fn trapped_rook(board: &Board, color: Color, rook: Square) -> bool {
+    let king = board.piece_board(Piece::King, color).get_first_square();
+
+    if board.castle_rights(color)
+        || relative_rank(king, color) != 0
+        || relative_rank(rook, color) != 0
+    {
+        return false;
+    }
+
+    // the rook must be between the king and the corner on its side of the board
+    let boxed_in = match file_of(king) {
+        4..=7 => file_of(rook) > file_of(king),
+        _ => file_of(rook) < file_of(king),
+    };
+
+    let mobility =
+        piece_attacks(Piece::Rook, color, rook, board.all_pieces()) & !board.color_board(color);
+
+    boxed_in && mobility.count_bits() <= TRAPPED_ROOK_MOBILITY
+}
+
+/// Rewards outposts for knights and bishops, and penalizes bishops restricted or trapped by pawns
+fn minor_pieces(
+    board: &Board,
+    color: Color,
+    attacks: &AttackMaps,
+    params: &PieceParams,
+) -> Tapered {
+    let own_pawns = board.piece_board(Piece::Pawn, color);
+    let enemy_pawns = board.piece_board(Piece::Pawn, color.opposite());
+    let own_pawn_attacks = attacks.by_piece(Piece::Pawn, color);
+
+    let mut score = Tapered::default();
+
+    for piece in [Piece::Knight, Piece::Bishop] {
+        for square in board.piece_board(piece, color) {
+            // enemy pawns on adjacent files in front of the piece could still chase it away
+            let chasers = PASSED_PAWN_MASKS[color][square] & ADJACENT_FILE_MASKS[file_of(square)];
+
+            let outpost = (3..=5).contains(&relative_rank(square, color))
+                && own_pawn_attacks.bit_at(square)
+                && (chasers & enemy_pawns).is_empty();
+
+            if outpost {
+                score += match piece {
+                    Piece::Knight => params.knight_outpost,
+                    _ => params.bishop_outpost,
+                };
+            }
+        }
+    }
+
+    for square in board.piece_board(Piece::Bishop, color) {
+        let same_color = |pawn: &Square| is_light_square(*pawn) == is_light_square(square);
+        let blocked = |pawn: &Square| board.all_pieces().bit_at(stop_square(*pawn, color));
+
+        let same_color_pawns = own_pawns.filter(same_color).count();
+        let blocked_pawns = own_pawns.filter(same_color).filter(blocked).count();
+
+        score += params.bad_bishop * same_color_pawns as Score;
+        score += params.blocked_bad_bishop * blocked_pawns as Score;
+
+        if trapped_bishop(board, color, square) {
+            score += params.trapped_bishop;
+        }
+    }
+
+    score
+}
+
+/// Returns true if the bishop is on a7/h7 (a2/h2 for black) and an enemy pawn blocks its way out
+fn trapped_bishop(board: &Board, color: Color, bishop: Square) -> bool {
+    // trapped square paired with the square of the pawn cutting it off, from white's point of view
+    const TRAPS: [(Square, Square); 2] = [(8, 17), (15, 22)]; // a7 and b6, h7 and g6
+
+    let enemy_pawns = board.piece_board(Piece::Pawn, color.opposite());
+
+    // squares are mirrored vertically for black
+    let relative = |square: Square| match color {
+        Color::White => square,
+        Color::Black => square ^ 56,
+    };
+
+    TRAPS
+        .into_iter()
+        .any(|(trap, pawn)| bishop == relative(trap) && enemy_pawns.bit_at(relative(pawn)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn white_pieces(fen: &str) -> Tapered {
+        let board = Board::new(fen);
+        evaluate_pieces(
+            &board,
+            Color::White,
+            &AttackMaps::new(&board),
+            &PieceParams::default(),
+        )
+    }
+
+    #[test]
+    fn test_rooks() {
+        let open = white_pieces("4k3/8/8/8/8/8/1P6/R3K3 w - - 0 1");
+        let semi_open = white_pieces("4k3/p7/8/8/8/8/1P6/R3K3 w - - 0 1");
+        let closed = white_pieces("4k3/8/8/8/8/8/P7/R3K3 w - - 0 1");
+        assert!(open.mg > semi_open.mg && semi_open.mg > closed.mg);
+
+        // rook in the corner behind a king that has moved instead of castling
+        let trapped = white_pieces("4k3/8/8/8/8/8/5PPP/5K1R w - - 0 1");
+        let castled = white_pieces("4k3/8/8/8/8/8/5PPP/5RK1 w - - 0 1");
+        assert_eq!(trapped - castled, PieceParams::default().trapped_rook);
+    }
+
+    #[test]
+    fn test_minor_pieces() {
+        // a knight on e5 defended by a pawn, which can't be chased away by the d and f pawns
+        let outpost = white_pieces("4k3/8/8/4N3/3P4/8/8/4K3 w - - 0 1");
+        let chased = white_pieces("4k3/5p2/8/4N3/3P4/8/8/4K3 w - - 0 1");
+        assert_eq!(outpost - chased, PieceParams::default().knight_outpost);
+
+        // bishop taking a pawn on a7 is trapped by b6
+        let trapped = white_pieces("4k3/B7/1p6/8/8/8/8/4K3 w - - 0 1");
+        let free = white_pieces("4k3/B7/8/1p6/8/8/8/4K3 w - - 0 1");
+        assert_eq!(trapped - free, PieceParams::default().trapped_bishop);
+
+        // bishop behind its own blocked pawns on the same color
+        let bad = white_pieces("4k3/8/8/3p4/3P4/8/8/2B1K3 w - - 0 1");
+        let good = white_pieces("4k3/8/8/4p3/4P3/8/8/2B1K3 w - - 0 1");
+        assert!(bad.mg < good.mg);
+    }
+}
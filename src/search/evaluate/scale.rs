@@ -0,0 +1,187 @@
+use super::masks::FILE_MASKS;
+use crate::{
+    board::Board,
+    core::{file_of, is_light_square, relative_rank, Bitboard, Color, Piece},
+    search::{tapered::Tapered, Score},
+};
+
+/// Scale factor that leaves the endgame score as it is, lower factors move the score towards a draw
+const SCALE_NORMAL: i32 = 64;
+
+// opposite colored bishops can't contest each other's squares, so extra pawns are very hard to convert
+const PURE_OPPOSITE_BISHOPS_SCALE: i32 = 16;
+const OPPOSITE_BISHOPS_SCALE: i32 = 44;
+
+/// Scale for pawns all on one side of the board, where the defender can give up a piece for the last pawns
+const ONE_WING_SCALE: i32 = 48;
+
+// without pawns, a side needs more than a minor piece extra to force mate
+const PAWNLESS_SCALE: i32 = 12;
+const PAWNLESS_NO_MATE_SCALE: i32 = 0;
+
+/// Scale for a rook and pawn against a rook with the defending king in front of the pawn
+const ROOK_PAWN_FORTRESS_SCALE: i32 = 8;
+
+/// Shrinks the endgame score of positions where the side that is ahead will struggle to actually win
+///
+/// The score is from white's point of view, and `passed` holds the passed pawns of both sides
+pub fn scale_endgame(board: &Board, score: Tapered, passed: Bitboard) -> Tapered {
+    let strong = match score.eg >= 0 {
+        true => Color::White,
+        false => Color::Black,
+    };
+
+    let factor = scale_factor(board, strong, passed);
+    let eg = score.eg as i32 * factor / SCALE_NORMAL;
+
+    Tapered::new(score.mg, eg as Score)
+}
+
+/// Finds the scale factor for the endgame score of the side that is ahead
+fn scale_factor(board: &Board, strong: Color, passed: Bitboard) -> i32 {
+    use Piece::*;
+
+    let weak = strong.opposite();
+
+    let strong_pawns = board.piece_board(Pawn, strong);
+    let weak_pawns = board.piece_board(Pawn, weak);
+    let strong_material = non_pawn_material(board, strong);
+    let weak_material = non_pawn_material(board, weak);
+
+    if strong_pawns.is_empty() && strong_material - weak_material <= Bishop.material_value() {
+        return match strong_material < Rook.material_value() {
+            true => PAWNLESS_NO_MATE_SCALE,
+            false => PAWNLESS_SCALE,
+        };
+    }
+
+    if rook_pawn_fortress(board, strong) {
+        return ROOK_PAWN_FORTRESS_SCALE;
+    }
+
+    if opposite_bishops(board) {
+        let bishops_only =
+            strong_material == Bishop.material_value() && weak_material == Bishop.material_value();
+
+        return match bishops_only {
+            true => PURE_OPPOSITE_BISHOPS_SCALE,
+            false => OPPOSITE_BISHOPS_SCALE,
+        };
+    }
+
+    let all_pawns = strong_pawns | weak_pawns;
+    let queenside = FILE_MASKS[0] | FILE_MASKS[1] | FILE_MASKS[2] | FILE_MASKS[3];
+    let one_wing = (all_pawns & queenside).is_empty() || (all_pawns & !queenside).is_empty();
+
+    if one_wing
+        && (passed & strong_pawns).is_empty()
+        && strong_pawns.count_bits() <= weak_pawns.count_bits() + 1
+    {
+        return ONE_WING_SCALE;
+    }
+
+    SCALE_NORMAL
+}
+
+/// Total material value of the given side's pieces, other than pawns
+///
+/// The fixed piece values are used on purpose rather than the tunable ones in `MaterialParams`. They are only compared
+/// against single piece values to recognise material configurations, such as being at most a minor piece up, and
+/// those shouldn't change when tuning moves the value of a piece.
+fn non_pawn_material(board: &Board, color: Color) -> Score {
+    use Piece::*;
+
+    [Knight, Bishop, Rook, Queen]
+        .into_iter()
+        .map(|piece| piece.material_value() * board.piece_board(piece, color).count_bits() as Score)
+        .sum()
+}
+
+/// Returns true if each side has a single bishop (and possibly other pieces), on different colored squares
+fn opposite_bishops(board: &Board) -> bool {
+    let white = board.piece_board(Piece::Bishop, Color::White);
+    let black = board.piece_board(Piece::Bishop, Color::Black);
+
+    white.count_bits() == 1
+        && black.count_bits() == 1
+        && is_light_square(white.get_first_square()) != is_light_square(black.get_first_square())
+}
+
+/// Returns true for a rook and pawn against a lone rook, where the defending king blocks the pawn's path
+fn rook_pawn_fortress(board: &Board, strong: Color) -> bool {
+    use Piece::*;
+
+    let weak = strong.opposite();
+
+    let material = |color: Color| board.color_board(color).count_bits();
+    let rook_and_pawn = board.piece_board(Rook, strong).count_bits() == 1
+        && board.piece_board(Pawn, strong).count_bits() == 1
+        && material(strong) == 3;
+    let lone_rook = board.piece_board(Rook, weak).count_bits() == 1 && material(weak) == 2;
+
+    if !rook_and_pawn || !lone_rook {
+        return false;
+    }
+
+    let pawn = board.piece_board(Pawn, strong).get_first_square();
+    let weak_king = board.piece_board(King, weak).get_first_square();
+
+    file_of(weak_king).abs_diff(file_of(pawn)) <= 1
+        && relative_rank(weak_king, strong) > relative_rank(pawn, strong)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn white_scale(fen: &str) -> i32 {
+        scale_factor(&Board::new(fen), Color::White, Bitboard::EMPTY)
+    }
+
+    #[test]
+    fn test_drawish_endgames() {
+        // a pawn up with opposite colored bishops, then with rooks still on the board
+        assert_eq!(
+            white_scale("4k3/5p2/8/2b5/8/3B4/5PP1/6K1 w - - 0 1"),
+            PURE_OPPOSITE_BISHOPS_SCALE
+        );
+        assert_eq!(
+            white_scale("r3k3/5p2/8/2b5/8/3B4/5PP1/R5K1 w - - 0 1"),
+            OPPOSITE_BISHOPS_SCALE
+        );
+
+        // rook against bishop without any pawns is usually a draw
+        assert_eq!(
+            white_scale("4k3/8/8/2b5/8/8/8/R5K1 w - - 0 1"),
+            PAWNLESS_SCALE
+        );
+
+        // defending king in front of the pawn
+        assert_eq!(
+            white_scale("4k3/8/8/4P3/8/8/r7/R5K1 w - - 0 1"),
+            ROOK_PAWN_FORTRESS_SCALE
+        );
+
+        // an extra pawn with all pawns on the kingside
+        assert_eq!(
+            white_scale("6k1/5pp1/8/8/8/8/5PPP/R5K1 w - - 0 1"),
+            ONE_WING_SCALE
+        );
+        assert_eq!(
+            white_scale("6k1/p4pp1/8/8/8/8/P4PPP/R5K1 w - - 0 1"),
+            SCALE_NORMAL
+        );
+    }
+
+    #[test]
+    fn test_scaled_score() {
+        let board = Board::new("4k3/5p2/8/2b5/8/3B4/5PP1/6K1 w - - 0 1");
+        let scaled = scale_endgame(&board, Tapered::new(100, 100), Bitboard::EMPTY);
+        assert_eq!(scaled, Tapered::new(100, 25));
+
+        // the factor is taken from the side that is ahead, and a lone bishop can never win
+        let board = Board::new("4k3/8/8/2b5/8/8/8/R5K1 w - - 0 1");
+        let scaled = scale_endgame(&board, Tapered::new(-100, -100), Bitboard::EMPTY);
+        assert_eq!(scaled, Tapered::new(-100, 0));
+    }
+}
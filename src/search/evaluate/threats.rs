@@ -0,0 +1,156 @@
+use super::{attacks::AttackMaps, params::eval_params, pawns::stop_square};
+use crate::{
+    board::{Board, PAWN_ATTACKS},
+    core::{relative_rank, Bitboard, Color, Piece},
+    search::{see::see_capture, tapered::Tapered, Score},
+};
+
+eval_params! {
+    /// Weights of the threats made against enemy pieces
+    pub struct ThreatParams {
+        /// Bonus for attacking a knight or bishop with a pawn, which forces the piece to move away
+        pawn_on_minor: Tapered = Tapered::new(60, 35),
+
+        /// Bonus for attacking a rook or queen with a less valuable piece, by the piece attacked
+        lesser_attacker: [Tapered; 6] = [
+            Tapered::new(0, 0),
+            Tapered::new(0, 0),
+            Tapered::new(0, 0),
+            Tapered::new(40, 50),
+            Tapered::new(50, 60),
+            Tapered::new(0, 0),
+        ],
+
+        /// Bonus for attacking a piece that can be captured for a gain in material, such as a piece without defenders
+        hanging_piece: Tapered = Tapered::new(35, 20),
+
+        /// Bonus for each piece that a pawn could safely push to attack
+        pawn_push: Tapered = Tapered::new(25, 20),
+    }
+}
+
+/// Evaluates the threats the given color makes against the opponent's pieces
+pub fn evaluate_threats(
+    board: &Board,
+    color: Color,
+    attacks: &AttackMaps,
+    params: &ThreatParams,
+) -> Tapered {
+    use Piece::*;
+
+    let enemy = color.opposite();
+    let own_attacks = attacks.all(color);
+    let enemy_attacks = attacks.all(enemy);
+
+    // pawns and kings are left out, pawn threats are covered by the pawn terms and kings can't be captured
+    let enemy_pieces = board.color_board(enemy)
+        & !board.piece_board(Pawn, enemy)
+        & !board.piece_board(King, enemy);
+
+    let mut score = Tapered::default();
+
+    let minors = board.piece_board(Knight, enemy) | board.piece_board(Bishop, enemy);
+    let pawn_threats = attacks.by_piece(Pawn, color) & minors;
+    score += params.pawn_on_minor * pawn_threats.count_bits() as Score;
+
+    for victim in [Rook, Queen] {
+        let lesser_attacks = [Pawn, Knight, Bishop, Rook]
+            .into_iter()
+            .filter(|attacker| attacker.material_value() < victim.material_value())
+            .fold(Bitboard::EMPTY, |lesser, attacker| {
+                lesser | attacks.by_piece(attacker, color)
+            });
+
+        let threatened = board.piece_board(victim, enemy) & lesser_attacks;
+        score += params.lesser_attacker[victim as usize] * threatened.count_bits() as Score;
+    }
+
+    // static exchange evaluation finds the pieces that are won by capturing them, defended or not
+    let hanging = (enemy_pieces & own_attacks)
+        .filter(|square| see_capture(board, *square, color) > 0)
+        .count();
+    score += params.hanging_piece * hanging as Score;
+
+    // squares a pawn can move to without being captured for free
+    let safe = !attacks.by_piece(Pawn, enemy) & (own_attacks | !enemy_attacks);
+    let push_threats = pawn_push_attacks(board, color, safe) & enemy_pieces & !pawn_threats;
+    score += params.pawn_push * push_threats.count_bits() as Score;
+
+    score
+}
+
+/// Squares that the given color's pawns would attack after a single or double push onto a safe square
+fn pawn_push_attacks(board: &Board, color: Color, safe: Bitboard) -> Bitboard {
+    let occupancy = board.all_pieces();
+    let mut threats = Bitboard::EMPTY;
+
+    for pawn in board.piece_board(Piece::Pawn, color) {
+        let single = stop_square(pawn, color);
+
+        if occupancy.bit_at(single) {
+            continue;
+        }
+
+        // pawns on their starting rank can also move two squares
+        let double = (relative_rank(pawn, color) == 1)
+            .then(|| stop_square(single, color))
+            .filter(|double| !occupancy.bit_at(*double));
+
+        for push in std::iter::once(single)
+            .chain(double)
+            .filter(|push| safe.bit_at(*push))
+        {
+            threats |= PAWN_ATTACKS[color][push];
+        }
+    }
+
+    threats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn white_threats(fen: &str) -> Tapered {
+        let board = Board::new(fen);
+        evaluate_threats(
+            &board,
+            Color::White,
+            &AttackMaps::new(&board),
+            &ThreatParams::default(),
+        )
+    }
+
+    #[test]
+    fn test_attacked_pieces() {
+        let params = ThreatParams::default();
+
+        // the knight on d5 hangs to the e4 pawn, and still does once the c6 pawn defends it, as taking it wins material
+        let hanging = white_threats("4k3/8/8/3n4/4P3/8/8/4K3 w - - 0 1");
+        let defended = white_threats("4k3/8/2p5/3n4/4P3/8/8/4K3 w - - 0 1");
+        assert_eq!(hanging, params.pawn_on_minor + params.hanging_piece);
+        assert_eq!(defended, hanging);
+
+        // but attacked by a knight instead, the exchange on d5 comes out even
+        let traded = white_threats("4k3/8/2p5/3n4/8/4N3/8/4K3 w - - 0 1");
+        assert_eq!(traded, Tapered::default());
+
+        // a defended queen attacked by a knight
+        let queen = white_threats("4k3/4p3/3q4/8/4N3/8/8/4K3 w - - 0 1");
+        assert_eq!(
+            queen,
+            params.lesser_attacker[Piece::Queen as usize] + params.hanging_piece
+        );
+    }
+
+    #[test]
+    fn test_pawn_push_threats() {
+        // pushing e2-e4 attacks the knight on d5, which is defended by the c6 pawn
+        let push = white_threats("4k3/8/2p5/3n4/8/8/4P3/4K3 w - - 0 1");
+        assert_eq!(push, ThreatParams::default().pawn_push);
+
+        // but not if the e4 square is covered by an enemy pawn
+        let unsafe_push = white_threats("4k3/8/2p5/3n1p2/8/8/4P3/4K3 w - - 0 1");
+        assert_eq!(unsafe_push, Tapered::default());
+    }
+}
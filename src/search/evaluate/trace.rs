@@ -0,0 +1,173 @@
+use super::MAX_EVAL;
+use crate::{
+    core::{Color, NUM_COLORS},
+    search::{
+        tapered::{Tapered, MAX_PHASE},
+        Score,
+    },
+};
+use std::fmt::Display;
+
+/// Breakdown of a position's evaluation into every term, with scores from white's point of view
+pub struct EvalTrace {
+    pub(super) terms: Vec<(&'static str, [Tapered; NUM_COLORS])>, // each side's own score for every term
+    pub(super) imbalance: Tapered,
+    pub(super) total: Tapered,  // sum of every term, before scaling
+    pub(super) scaled: Tapered, // total after drawish endgames are scaled down
+    pub(super) phase: i32,
+
+    // evaluation used in place of the hand-crafted terms, from the side to move's point of view
+    pub(super) replaced: Option<(&'static str, Score)>,
+    pub(super) side: Color,
+}
+
+impl EvalTrace {
+    /// Final score from the side to move's point of view, as given by `evaluate`
+    pub fn score(&self) -> Score {
+        match self.replaced {
+            Some((_, score)) => score,
+            None => match self.side {
+                Color::White => self.hand_crafted(),
+                Color::Black => -self.hand_crafted(),
+            },
+        }
+    }
+
+    /// Final score from white's point of view
+    pub fn white_score(&self) -> Score {
+        match self.side {
+            Color::White => self.score(),
+            Color::Black => -self.score(),
+        }
+    }
+
+    /// Score of the hand-crafted terms from white's point of view, blended by the phase of the game
+    fn hand_crafted(&self) -> Score {
+        self.scaled
+            .interpolate(self.phase)
+            .clamp(-MAX_EVAL, MAX_EVAL)
+    }
+}
+
+impl Display for EvalTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let row = |name: &str, columns: [Option<Tapered>; 3]| {
+            let mut line = format!("{:<16}", name);
+
+            for column in columns {
+                line.push_str(&match column {
+                    Some(score) => format!("| {:>6} {:>6} ", score.mg, score.eg),
+                    None => format!("| {:>6} {:>6} ", "-", "-"),
+                });
+            }
+
+            line.trim_end().to_string()
+        };
+        let separator = format!(
+            "{}\n",
+            [
+                "-".repeat(16),
+                "-".repeat(15),
+                "-".repeat(15),
+                "-".repeat(15)
+            ]
+            .join("+")
+        );
+
+        writeln!(
+            f,
+            "{:<16}| {:^13} | {:^13} | {:^13}",
+            "Term", "White", "Black", "Total"
+        )?;
+        writeln!(
+            f,
+            "{:<16}| {:>6} {:>6} | {:>6} {:>6} | {:>6} {:>6}",
+            "", "MG", "EG", "MG", "EG", "MG", "EG"
+        )?;
+        write!(f, "{}", separator)?;
+
+        for (name, [white, black]) in self.terms.iter() {
+            writeln!(
+                f,
+                "{}",
+                row(name, [Some(*white), Some(*black), Some(*white - *black)])
+            )?;
+        }
+
+        writeln!(
+            f,
+            "{}",
+            row("Imbalance", [None, None, Some(self.imbalance)])
+        )?;
+        write!(f, "{}", separator)?;
+        writeln!(f, "{}", row("Total", [None, None, Some(self.total)]))?;
+        writeln!(f, "{}", row("Scaled", [None, None, Some(self.scaled)]))?;
+        writeln!(f)?;
+
+        writeln!(f, "Phase: {}/{}", self.phase, MAX_PHASE)?;
+        writeln!(
+            f,
+            "Hand-crafted evaluation: {} (white)",
+            self.hand_crafted()
+        )?;
+
+        if let Some((name, score)) = self.replaced {
+            writeln!(
+                f,
+                "{} evaluation: {} (side to move), used instead",
+                name, score
+            )?;
+        }
+
+        writeln!(
+            f,
+            "Final evaluation: {} (white), {} (side to move)",
+            self.white_score(),
+            self.score()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        board::Board,
+        search::{
+            evaluate::{evaluate, trace},
+            EvalParams, EvalTables,
+        },
+    };
+
+    #[test]
+    fn test_trace() {
+        let params = EvalParams::default();
+        let mut tables = EvalTables::new();
+
+        // middlegames, a scaled endgame and a specialised endgame, with either side to move
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+            "8/5k2/8/2b5/8/4B3/5PK1/8 b - - 0 1",
+            "8/8/8/4k3/8/8/2KQ4/8 w - - 0 1",
+        ] {
+            let board = Board::new(fen);
+            let trace = trace(&board, &params, &mut tables);
+
+            assert_eq!(
+                trace.score(),
+                evaluate(&board, &params, &mut tables),
+                "{}",
+                fen
+            );
+
+            // the terms of both sides add up to the total
+            let sum = trace
+                .terms
+                .iter()
+                .fold(trace.imbalance, |score, (_, [white, black])| {
+                    score + *white - *black
+                });
+            assert_eq!(sum, trace.total);
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use super::{
+    evaluate::{evaluate, params::EvalParams, trace, EvalTables, EvalTrace},
+    Score,
+};
+use crate::{
+    board::{Board, Move},
+    core::ALL_PIECES,
+};
+use std::fmt::Display;
+
+/// Scores positions for the search, from the side to move's point of view
+///
+/// The search tells the evaluator about every move it makes and unmakes, so that an evaluator can update its state
+/// incrementally instead of looking at the whole board for every position. Evaluators that don't need this can
+/// ignore the hooks.
+pub trait Evaluator {
+    /// Evaluates the board position and assigns a value representing the active side's advantage
+    fn evaluate(&mut self, board: &Board) -> Score;
+
+    /// Called at the start of every search with the position being searched from
+    fn start_search(&mut self, _board: &Board) {}
+
+    /// Called once a search is finished, with the board back in the position it was searched from
+    fn end_search(&mut self, _board: &Board) {}
+
+    /// Called after the search makes a move, with the board already in the position after it
+    fn make_move(&mut self, _board: &Board, _mov: Move) {}
+
+    /// Called after the search unmakes its last move, with the board already back in the position before it
+    fn unmake_move(&mut self, _board: &Board) {}
+
+    /// Called after the search passes the turn without moving, with the other side already to move
+    fn make_null_move(&mut self, _board: &Board) {}
+
+    /// Called after the search undoes its last null move
+    fn unmake_null_move(&mut self, _board: &Board) {}
+}
+
+/// Hand-crafted evaluation, along with its weights and the caches it keeps between positions
+pub struct ClassicalEvaluator {
+    params: EvalParams,
+    tables: EvalTables,
+}
+
+impl ClassicalEvaluator {
+    pub fn new(params: EvalParams) -> ClassicalEvaluator {
+        ClassicalEvaluator {
+            params,
+            tables: EvalTables::new(),
+        }
+    }
+
+    /// Replaces the weights of the evaluation, clearing every cache holding scores from the old weights
+    pub fn set_params(&mut self, params: EvalParams) {
+        self.params = params;
+        self.tables = EvalTables::with_cache_size(self.tables.cache_size());
+    }
+
+    /// Replaces the evaluation cache with an empty one of the given size in MB
+    pub fn set_cache_size(&mut self, cache_size: usize) {
+        self.tables = EvalTables::with_cache_size(cache_size);
+    }
+
+    /// Breaks the evaluation of the board down into every term
+    pub fn trace(&mut self, board: &Board) -> EvalTrace {
+        trace(board, &self.params, &mut self.tables)
+    }
+}
+
+impl Default for ClassicalEvaluator {
+    fn default() -> Self {
+        Self::new(EvalParams::default())
+    }
+}
+
+impl Evaluator for ClassicalEvaluator {
+    fn evaluate(&mut self, board: &Board) -> Score {
+        evaluate(board, &self.params, &mut self.tables)
+    }
+}
+
+impl Display for ClassicalEvaluator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.tables)
+    }
+}
+
+/// Counts material and nothing else, for testing the search without the rest of the evaluation getting in the way
+#[derive(Default)]
+pub struct MaterialEvaluator;
+
+impl Evaluator for MaterialEvaluator {
+    fn evaluate(&mut self, board: &Board) -> Score {
+        let side = board.active_color();
+
+        ALL_PIECES
+            .iter()
+            .map(|piece| {
+                let own = board.piece_board(*piece, side).count_bits() as Score;
+                let enemy = board.piece_board(*piece, side.opposite()).count_bits() as Score;
+
+                (own - enemy) * piece.material_value()
+            })
+            .sum()
+    }
+}
+
+/// Evaluates a position along with its mirrored positions, which a symmetric evaluation scores exactly the same
+///
+/// Returns the score of the position, and each mirrored position that was scored differently along with its score.
+pub fn symmetry_mismatches<E: Evaluator>(
+    evaluator: &mut E,
+    board: &Board,
+) -> (Score, Vec<(Board, Score)>) {
+    let score = evaluator.evaluate(board);
+
+    let mismatches = [Some(board.mirrored()), board.mirrored_files()]
+        .into_iter()
+        .flatten()
+        .map(|mirrored| {
+            let mirrored_score = evaluator.evaluate(&mirrored);
+            (mirrored, mirrored_score)
+        })
+        .filter(|(_, mirrored_score)| *mirrored_score != score)
+        .collect();
+
+    (score, mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::Searcher;
+
+    /// Counts the hooks called by the search, checking that every move made is unmade again
+    #[derive(Default)]
+    struct CountingEvaluator {
+        depth: usize,
+        made: usize,
+    }
+
+    impl Evaluator for CountingEvaluator {
+        fn evaluate(&mut self, board: &Board) -> Score {
+            MaterialEvaluator.evaluate(board)
+        }
+
+        fn start_search(&mut self, _board: &Board) {
+            assert_eq!(self.depth, 0);
+        }
+
+        fn end_search(&mut self, _board: &Board) {
+            assert_eq!(self.depth, 0);
+        }
+
+        fn make_move(&mut self, _board: &Board, _mov: Move) {
+            self.depth += 1;
+            self.made += 1;
+        }
+
+        fn unmake_move(&mut self, _board: &Board) {
+            self.depth -= 1;
+        }
+
+        fn make_null_move(&mut self, _board: &Board) {
+            self.depth += 1;
+        }
+
+        fn unmake_null_move(&mut self, _board: &Board) {
+            self.depth -= 1;
+        }
+    }
+
+    /// Searches a position for a fixed amount of nodes, returning the best move found
+    fn search<E: Evaluator>(searcher: &mut Searcher<E>, fen: &str) -> Move {
+        searcher.set_node_limit(Some(2000));
+        *searcher.get_search_control().lock().unwrap() = true;
+
+        searcher.best_move(&mut Board::new(fen)).unwrap().0
+    }
+
+    #[test]
+    fn test_evaluators() {
+        // white is a rook up, which is all the material evaluator sees
+        let fen = "4k3/8/8/8/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(MaterialEvaluator.evaluate(&Board::new(fen)), 500);
+        assert_eq!(
+            MaterialEvaluator.evaluate(&Board::new("4k3/8/8/8/8/8/8/R3K3 b - - 0 1")),
+            -500
+        );
+
+        // a free rook is taken with either evaluator
+        let fen = "4k3/8/8/3r4/8/8/8/3RK3 w - - 0 1";
+        assert!(search(&mut Searcher::new(1), fen).is_capture());
+        assert!(search(&mut Searcher::with_evaluator(1, MaterialEvaluator), fen).is_capture());
+
+        // every move the search makes is reported to the evaluator, and undone by the end
+        let mut searcher = Searcher::with_evaluator(1, CountingEvaluator::default());
+        search(&mut searcher, fen);
+        assert_eq!(searcher.evaluator().depth, 0);
+        assert!(searcher.evaluator().made > 0);
+    }
+
+    #[test]
+    fn test_eval_symmetry() {
+        let mut evaluator = ClassicalEvaluator::default();
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "2r3k1/1q3ppp/p2b4/1p1Pp3/4P1n1/P1N1BN2/1P1Q1PPP/3R2K1 b - - 0 1",
+            "8/5k2/8/2b5/8/4B3/5PK1/8 b - - 0 1",
+            "8/8/8/4k3/8/8/2KQ4/8 w - - 0 1",
+        ] {
+            let (_, mismatches) = symmetry_mismatches(&mut evaluator, &Board::new(fen));
+            assert!(mismatches.is_empty(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_eval_cache() {
+        let mut cached = ClassicalEvaluator::default();
+        let mut uncached = ClassicalEvaluator::default();
+        uncached.set_cache_size(0);
+
+        // a cached evaluation is the same as evaluating again, for either side to move
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1",
+        ] {
+            let board = Board::new(fen);
+            let score = uncached.evaluate(&board);
+
+            assert_eq!(cached.evaluate(&board), score);
+            assert_eq!(cached.evaluate(&board), score);
+        }
+
+        assert!(cached.to_string().contains("eval cache (16 MB)"));
+        assert!(cached.to_string().contains("hits (rate %): 2 "));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{
+    board::Move,
+    core::{Color, BOARD_SIZE, NUM_COLORS},
+};
+
+/// Largest magnitude a history score can reach, bonuses shrink as a score gets closer to it
+pub const MAX_HISTORY: i32 = 8192;
+
+/// Largest bonus or penalty given for a single cutoff, reached at a depth of about 35
+const MAX_HISTORY_BONUS: i32 = 1200;
+
+/// Amount of plies that killer moves are kept for, one for each ply the search can reach
+const MAX_PLY: usize = u8::MAX as usize + 1;
+
+/// Quiet moves that caused a beta cutoff at each ply, which often cause one again in sibling positions
+pub struct KillerMoves {
+    slots: Vec<[Option<Move>; 2]>,
+}
+
+impl KillerMoves {
+    pub fn new() -> KillerMoves {
+        KillerMoves {
+            slots: vec![[None; 2]; MAX_PLY],
+        }
+    }
+
+    /// Both killer moves at a ply, the most recent one first
+    pub fn get(&self, ply: u8) -> [Option<Move>; 2] {
+        self.slots[ply as usize]
+    }
+
+    /// Stores a move that caused a cutoff, pushing out the older of the two killers at that ply
+    pub fn insert(&mut self, ply: u8, mov: Move) {
+        let slots = &mut self.slots[ply as usize];
+
+        // keep two different moves instead of the same one twice
+        if slots[0] != Some(mov) {
+            slots[1] = slots[0];
+            slots[0] = Some(mov);
+        }
+    }
+
+    /// Forgets every killer move, since they only relate to the positions of a single search
+    pub fn clear(&mut self) {
+        self.slots.fill([None; 2]);
+    }
+}
+
+impl Default for KillerMoves {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Butterfly history table, scoring quiet moves by how often they caused cutoffs, indexed by color, from and to squares
+pub struct HistoryTable {
+    scores: Box<[[[i16; BOARD_SIZE]; BOARD_SIZE]; NUM_COLORS]>,
+}
+
+impl HistoryTable {
+    pub fn new() -> HistoryTable {
+        HistoryTable {
+            scores: Box::new([[[0; BOARD_SIZE]; BOARD_SIZE]; NUM_COLORS]),
+        }
+    }
+
+    /// History score of a quiet move made by the given color
+    pub fn get(&self, color: Color, mov: Move) -> i16 {
+        self.scores[color as usize][mov.from][mov.to]
+    }
+
+    /// Rewards a quiet move that caused a cutoff at the given depth, deeper cutoffs counting for more
+    pub fn reward(&mut self, color: Color, mov: Move, depth: u8) {
+        self.update(color, mov, Self::bonus(depth));
+    }
+
+    /// Penalizes a quiet move that was searched before the move causing a cutoff, but failed to cause one itself
+    pub fn penalize(&mut self, color: Color, mov: Move, depth: u8) {
+        self.update(color, mov, -Self::bonus(depth));
+    }
+
+    /// Halves every score, so that moves from older searches slowly make way for new ones
+    pub fn age(&mut self) {
+        self.scores
+            .iter_mut()
+            .flatten()
+            .flatten()
+            .for_each(|score| *score /= 2);
+    }
+
+    fn bonus(depth: u8) -> i32 {
+        (depth as i32 * depth as i32).min(MAX_HISTORY_BONUS)
+    }
+
+    /// Applies a bonus with gravity, which pulls the score back towards zero the closer it is to `MAX_HISTORY`
+    ///
+    /// This keeps every score within `MAX_HISTORY`, and lets moves that stop causing cutoffs lose their score quickly.
+    fn update(&mut self, color: Color, mov: Move, bonus: i32) {
+        let score = &mut self.scores[color as usize][mov.from][mov.to];
+        let gravity = *score as i32 * bonus.abs() / MAX_HISTORY;
+
+        *score += (bonus - gravity) as i16;
+    }
+}
+
+impl Default for HistoryTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+
+    #[test]
+    fn test_killers_and_history() {
+        let board = Board::default();
+        let moves = board.generate_moves();
+        let (first, second, third) = (moves[0], moves[1], moves[2]);
+
+        // the two most recent different killers are kept
+        let mut killers = KillerMoves::new();
+        killers.insert(3, first);
+        killers.insert(3, first);
+        killers.insert(3, second);
+        assert_eq!(killers.get(3), [Some(second), Some(first)]);
+
+        killers.insert(3, third);
+        assert_eq!(killers.get(3), [Some(third), Some(second)]);
+        assert_eq!(killers.get(4), [None, None]);
+
+        // rewards and penalties push scores either way, but never past the limit
+        let mut history = HistoryTable::new();
+        history.reward(Color::White, first, 5);
+        history.penalize(Color::White, second, 5);
+        assert!(history.get(Color::White, first) > 0);
+        assert!(history.get(Color::White, second) < 0);
+        assert_eq!(history.get(Color::Black, first), 0);
+
+        for _ in 0..1000 {
+            history.reward(Color::White, first, 40);
+            history.penalize(Color::White, second, 40);
+        }
+
+        assert!((history.get(Color::White, first) as i32) <= MAX_HISTORY);
+        assert!((history.get(Color::White, second) as i32) >= -MAX_HISTORY);
+
+        // a single penalty takes more off a high score than it would off a low one
+        let high = history.get(Color::White, first);
+        history.penalize(Color::White, first, 10);
+        assert!(high - history.get(Color::White, first) > 100);
+    }
+}
@@ -1,10 +1,18 @@
 mod alpha_beta;
 mod evaluate;
+mod evaluator;
+mod history;
 mod ordering;
 mod pst;
+mod reductions;
+mod see;
+mod tapered;
 mod tt;
 
 pub use alpha_beta::{SearchTT, Searcher};
+pub use evaluate::{evaluate, params::EvalParams, EvalTables, EvalTrace, EVAL_CACHE_SIZE};
+pub use evaluator::{symmetry_mismatches, ClassicalEvaluator, Evaluator, MaterialEvaluator};
+pub use see::{see, see_ge};
 
 /// Represents the score of the board, where a positive number implies moving side is ahead
 pub type Score = i16;
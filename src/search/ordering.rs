@@ -1,16 +1,32 @@
+use super::{history::HistoryTable, see::see_ge};
 use crate::{
-    board::{Move, MoveFlag},
+    board::{Board, Move, MoveFlag},
     core::Piece,
 };
 
-// TODO - as of now, sort_by_cached_key is slower than sort_by_key, if this importance calculation grows, it may change
+/// Taken off the importance of captures that lose material, which places them after every quiet move
+const BAD_CAPTURE_PENALTY: i16 = 10000;
+
+/// Added to the importance of captures that don't lose material and promotions, placing them before every quiet move
+const GOOD_CAPTURE_BONUS: i16 = 10000;
+
+/// Importance of the most recent killer move, the older one is placed right after it, both ahead of other quiet moves
+const KILLER_IMPORTANCE: i16 = 9000;
 
 /// Orders the moves in a given list according to the likelihood of the move being good
 ///
-/// Optionally accepts a best move to place at the very start of the list
-pub fn order_moves(moves: &mut Vec<Move>, best_move: Option<Move>) {
-    // generate an approximate importance value per move and sort by it
-    moves.sort_by_key(|mov| {
+/// Optionally accepts a best move to place at the very start of the list. Good captures and promotions come next,
+/// then the killer moves of this ply, then every other quiet move by its history score, and losing captures last.
+pub fn order_moves(
+    moves: &mut [Move],
+    board: &Board,
+    best_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    history: &HistoryTable,
+) {
+    // generate an approximate importance value per move and sort by it, the static exchange evaluation makes this
+    // costly enough that each value is only worth calculating once
+    moves.sort_by_cached_key(|mov| {
         use MoveFlag::*;
 
         let mut importance = 0;
@@ -27,15 +43,29 @@ pub fn order_moves(moves: &mut Vec<Move>, best_move: Option<Move>) {
         // prefer attacking valuable opposing pieces with less valuable friendly pieces
         if attacked_value != 0 {
             importance += (5 * attacked_value) - moving_value;
+
+            // captures that lose material once the opponent takes back are tried last
+            importance += match see_ge(board, *mov, 0) {
+                true => GOOD_CAPTURE_BONUS,
+                false => -BAD_CAPTURE_PENALTY,
+            };
         }
 
         // prefer promotions
         importance += match mov.flag {
-            Promotion(promoted_piece) => promoted_piece.material_value(),
+            Promotion(promoted_piece) => GOOD_CAPTURE_BONUS + promoted_piece.material_value(),
             CapturePromotion(_, promoted_piece) => promoted_piece.material_value(),
             _ => 0,
         };
 
+        // quiet moves that caused cutoffs in sibling positions come first, then the ones that caused them most often
+        if mov.is_quiet() {
+            importance = match killers.iter().position(|killer| *killer == Some(*mov)) {
+                Some(slot) => KILLER_IMPORTANCE - slot as i16,
+                None => history.get(board.active_color(), *mov),
+            };
+        }
+
         // if there is a previously found best move, it should be at the front
         if best_move == Some(*mov) {
             importance = i16::MAX;
@@ -47,3 +77,40 @@ pub fn order_moves(moves: &mut Vec<Move>, best_move: Option<Move>) {
     // finally, reverse the ordering of moves because we want highest importance first
     moves.reverse();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order_moves() {
+        // the rook can take a pawn defended by another pawn, and the queen can take an undefended knight
+        let board = Board::new("4k3/8/2p5/3p1n2/8/8/8/3RKQ2 w - - 0 1");
+        let parse = |mov: &str| board.parse_move(mov).unwrap();
+
+        let mut history = HistoryTable::new();
+        history.reward(board.active_color(), parse("f1a6"), 10);
+        history.penalize(board.active_color(), parse("d1a1"), 10);
+
+        let mut moves = board.generate_moves();
+        order_moves(
+            &mut moves,
+            &board,
+            Some(parse("e1e2")),
+            [Some(parse("f1h3")), Some(parse("d1c1"))],
+            &history,
+        );
+
+        let position = |mov: &str| moves.iter().position(|m| *m == parse(mov)).unwrap();
+
+        // the best move comes first, then the winning capture, the killers, quiet moves by their history, and the
+        // losing capture after every quiet move
+        assert_eq!(position("e1e2"), 0);
+        assert_eq!(position("f1f5"), 1);
+        assert_eq!(position("f1h3"), 2);
+        assert_eq!(position("d1c1"), 3);
+        assert_eq!(position("f1a6"), 4);
+        assert_eq!(position("d1a1"), moves.len() - 2);
+        assert_eq!(position("d1d5"), moves.len() - 1);
+    }
+}
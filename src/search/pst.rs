@@ -1,11 +1,13 @@
-use super::Score;
-use crate::core::{Color, Piece, BOARD_SIZE, Square};
+use super::{evaluate::params::eval_params, tapered::Tapered, Score};
+use crate::core::{Color, Piece, BOARD_SIZE, NUM_PIECES, Square};
 
 type PieceSquareTable = [Score; BOARD_SIZE];
 
+// midgame tables
+
 // pawns should heavily prioritize promotion
 // C and D pawns should also be moved forward to take center control
-const PAWN_TABLE: PieceSquareTable = [
+const MG_PAWN_TABLE: PieceSquareTable = [
      0,   0,   0,   0,   0,   0,   0,   0,
     50,  50,  50,  50,  50,  50,  50,  50,
     10,  10,  20,  30,  30,  20,  10,  10,
@@ -17,7 +19,7 @@ const PAWN_TABLE: PieceSquareTable = [
 ];
 
 // knights should prefer central positions where they can control more squares
-const KNIGHT_TABLE: PieceSquareTable = [
+const MG_KNIGHT_TABLE: PieceSquareTable = [
     -50, -40, -30, -30, -30, -30, -40, -50,
     -40, -20,   0,   0,   0,   0, -20, -40,
     -30,   0,  10,  15,  15,  10,   0, -30,
@@ -29,7 +31,7 @@ const KNIGHT_TABLE: PieceSquareTable = [
 ];
 
 // bishops also generally want to be positioned slightly centrally, where they can control the most squares
-const BISHOP_TABLE: PieceSquareTable = [
+const MG_BISHOP_TABLE: PieceSquareTable = [
     -20, -10, -10, -10, -10, -10, -10, -20,
     -10,   0,   0,   0,   0,   0,   0, -10,
     -10,   0,   5,  10,  10,   5,   0, -10,
@@ -41,7 +43,7 @@ const BISHOP_TABLE: PieceSquareTable = [
 ];
 
 // rooks prefer the seventh rank of their side and generally avoid staying on their home columns
-const ROOK_TABLE: PieceSquareTable = [
+const MG_ROOK_TABLE: PieceSquareTable = [
      0,   0,   0,   0,   0,   0,   0,   0,
      5,  10,  10,  10,  10,  10,  10,   5,
     -5,   0,   0,   0,   0,   0,   0,  -5,
@@ -53,7 +55,7 @@ const ROOK_TABLE: PieceSquareTable = [
 ];
 
 // queens don't have too many rules, as they are very versatile and can attack many squares from anywhere
-const QUEEN_TABLE: PieceSquareTable = [
+const MG_QUEEN_TABLE: PieceSquareTable = [
     -20, -10, -10,  -5,  -5, -10, -10, -20,
     -10,   0,   0,   0,   0,   0,   0, -10,
     -10,   0,   5,   5,   5,   5,   0, -10,
@@ -65,7 +67,7 @@ const QUEEN_TABLE: PieceSquareTable = [
 ];
 
 // kings want to castle and stay behind their pawns
-const KING_TABLE: PieceSquareTable = [
+const MG_KING_TABLE: PieceSquareTable = [
     -30, -40, -40, -50, -50, -40, -40, -30,
     -30, -40, -40, -50, -50, -40, -40, -30,
     -30, -40, -40, -50, -50, -40, -40, -30,
@@ -76,29 +78,114 @@ const KING_TABLE: PieceSquareTable = [
      20,  30,  10,   0,   0,  10,  30,  20,
 ];
 
+// endgame tables
+
+// with fewer pieces left to stop them, advanced pawns become much more valuable
+const EG_PAWN_TABLE: PieceSquareTable = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    80,  80,  80,  80,  80,  80,  80,  80,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    30,  30,  30,  30,  30,  30,  30,  30,
+    15,  15,  15,  15,  15,  15,  15,  15,
+     5,   5,   5,   5,   5,   5,   5,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+// knights are short ranged, so they are still best kept near the center
+const EG_KNIGHT_TABLE: PieceSquareTable = [
+    -50, -40, -30, -30, -30, -30, -40, -50,
+    -40, -20, -10, -10, -10, -10, -20, -40,
+    -30, -10,  10,  15,  15,  10, -10, -30,
+    -30, -10,  15,  20,  20,  15, -10, -30,
+    -30, -10,  15,  20,  20,  15, -10, -30,
+    -30, -10,  10,  15,  15,  10, -10, -30,
+    -40, -20, -10, -10, -10, -10, -20, -40,
+    -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+// bishops are less restricted in open endgames, but still slightly prefer the center
+const EG_BISHOP_TABLE: PieceSquareTable = [
+    -15, -10, -10, -10, -10, -10, -10, -15,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   0,   5,  10,  10,   5,   0, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   0,   0,   0,   0,   0,   0, -10,
+    -15, -10, -10, -10, -10, -10, -10, -15,
+];
+
+// rooks on the seventh rank are still strong, otherwise their placement matters little
+const EG_ROOK_TABLE: PieceSquareTable = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    10,  10,  10,  10,  10,  10,  10,  10,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+// queens are most effective in the center, where they can reach the whole board
+const EG_QUEEN_TABLE: PieceSquareTable = [
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -10,   5,  10,  10,  10,  10,   5, -10,
+     -5,   5,  10,  15,  15,  10,   5,  -5,
+     -5,   5,  10,  15,  15,  10,   5,  -5,
+    -10,   5,  10,  10,  10,  10,   5, -10,
+    -10,   0,   5,   5,   5,   5,   0, -10,
+    -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+// with little danger of being mated, the king becomes an active piece and should centralize
+const EG_KING_TABLE: PieceSquareTable = [
+    -50, -40, -30, -20, -20, -30, -40, -50,
+    -30, -20, -10,   0,   0, -10, -20, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  30,  40,  40,  30, -10, -30,
+    -30, -10,  20,  30,  30,  20, -10, -30,
+    -30, -30,   0,   0,   0,   0, -30, -30,
+    -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+eval_params! {
+    /// Midgame and endgame piece-square tables for each piece, describing how generally well-positioned that piece is
+    ///
+    /// Midgame scores are fetched from https://www.chessprogramming.org/Simplified_Evaluation_Function#Piece-Square_Tables
+    pub struct PstParams {
+        mg: [PieceSquareTable; NUM_PIECES] = [
+            MG_PAWN_TABLE,
+            MG_KNIGHT_TABLE,
+            MG_BISHOP_TABLE,
+            MG_ROOK_TABLE,
+            MG_QUEEN_TABLE,
+            MG_KING_TABLE,
+        ],
+        eg: [PieceSquareTable; NUM_PIECES] = [
+            EG_PAWN_TABLE,
+            EG_KNIGHT_TABLE,
+            EG_BISHOP_TABLE,
+            EG_ROOK_TABLE,
+            EG_QUEEN_TABLE,
+            EG_KING_TABLE,
+        ],
+    }
+}
+
+impl PstParams {
+    /// Fetches the midgame and endgame piece-square table scores of a piece on a square
+    pub fn get(&self, piece: Piece, color: Color, square: Square) -> Tapered {
+        // for all positions, white and black are just mirrors of eachother (i.e. rank 1 white = rank 8 black)
+        let square = match color {
+            Color::White => square,
+            Color::Black => flip(square),
+        };
 
-// TODO - consider differences in game state, such as early vs. mid vs. end games and how the tables would be different
-// TODO - add function to flip the boards so that only one board needs to be kept up-to-date here
-/// Maps a piece and color to a piece-square table, describing how generally well-positioned that piece is
-///
-/// Scores are fetched from https://www.chessprogramming.org/Simplified_Evaluation_Function#Piece-Square_Tables
-pub const fn piece_square_table(piece: Piece, color: Color, square: Square) -> Score {
-    use Color::*;
-    use Piece::*;
-
-    // for all positions, white and black are just mirrors of eachother (i.e. rank 1 white = rank 8 black)
-    let table = match piece {
-        Pawn => PAWN_TABLE,
-        Knight => KNIGHT_TABLE,
-        Bishop => BISHOP_TABLE,
-        Rook => ROOK_TABLE,
-        Queen => QUEEN_TABLE,
-        King => KING_TABLE,
-    };
-
-    match color {
-        White => table[square],
-        Black => table[flip(square)],
+        Tapered::new(self.mg[piece as usize][square], self.eg[piece as usize][square])
     }
 }
 
@@ -0,0 +1,73 @@
+use lazy_static::lazy_static;
+
+// Shape of the late move reduction table, reductions grow with the logarithm of both the depth and the move index
+const LMR_BASE: f32 = 0.75;
+const LMR_DIVISOR: f32 = 2.25;
+
+/// Depths and move indices past the end of the table share its last entries
+const LMR_TABLE_SIZE: usize = 64;
+
+/// Amount of history score worth a single ply of reduction, quiet moves with a good history are reduced less
+const LMR_HISTORY_DIVISOR: i16 = 4096;
+
+lazy_static! {
+    /// Reduction of a late quiet move, by the remaining depth and the index of the move in the ordered move list
+    static ref LMR_TABLE: [[u8; LMR_TABLE_SIZE]; LMR_TABLE_SIZE] = generate_lmr_table();
+}
+
+fn generate_lmr_table() -> [[u8; LMR_TABLE_SIZE]; LMR_TABLE_SIZE] {
+    let mut table = [[0; LMR_TABLE_SIZE]; LMR_TABLE_SIZE];
+
+    for (depth, row) in table.iter_mut().enumerate().skip(1) {
+        for (index, reduction) in row.iter_mut().enumerate().skip(1) {
+            *reduction = (LMR_BASE + (depth as f32).ln() * (index as f32).ln() / LMR_DIVISOR) as u8;
+        }
+    }
+
+    table
+}
+
+/// Amount of depth a late quiet move is searched with less than the other moves
+///
+/// Moves on the principal variation and killer moves are reduced less, and the history score of the move moves the
+/// reduction either way. At least a single ply is always left to search.
+pub fn late_move_reduction(
+    depth: u8,
+    move_index: usize,
+    pv_node: bool,
+    killer: bool,
+    history: i16,
+) -> u8 {
+    let mut reduction = LMR_TABLE[(depth as usize).min(LMR_TABLE_SIZE - 1)]
+        [move_index.min(LMR_TABLE_SIZE - 1)] as i16;
+
+    reduction -= pv_node as i16 + killer as i16;
+    reduction -= history / LMR_HISTORY_DIVISOR;
+
+    reduction.clamp(0, depth.saturating_sub(2) as i16) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_late_move_reduction() {
+        let reduction = |depth, move_index| late_move_reduction(depth, move_index, false, false, 0);
+
+        // later moves at higher depths are reduced the most
+        assert!(reduction(10, 30) > reduction(4, 30));
+        assert!(reduction(10, 30) > reduction(10, 4));
+        assert!(late_move_reduction(10, 30, true, false, 0) < reduction(10, 30));
+
+        // killers and moves with a good history are reduced less, moves with a bad history more
+        assert!(late_move_reduction(10, 30, false, true, 0) < reduction(10, 30));
+        assert!(late_move_reduction(10, 30, false, false, 8000) < reduction(10, 30));
+        assert!(late_move_reduction(10, 30, false, false, -8000) > reduction(10, 30));
+
+        // a reduced move is never searched straight into the quiescence search
+        for depth in 2..=u8::MAX {
+            assert!(depth - late_move_reduction(depth, 200, false, false, -8192) > 1);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use super::Score;
+use crate::{
+    board::{
+        Board, Move, MoveFlag, BISHOP_MAGICS, KING_MOVES, KNIGHT_MOVES, PAWN_ATTACKS, ROOK_MAGICS,
+    },
+    core::{Bitboard, Color, Piece, Square, ALL_PIECES},
+};
+
+/// Most exchanges that can happen on a single square, one for each piece on the board
+const MAX_EXCHANGES: usize = 32;
+
+/// Static exchange evaluation, the material a move wins or loses once every capture back and forth on its target
+/// square has been made
+///
+/// Each side captures with its least valuable piece first and can stop capturing whenever that is better for it,
+/// sliding pieces behind the ones that have captured join in through x-rays. Pins are not taken into account. The
+/// move can be made by either side, not just the side to move.
+pub fn see(board: &Board, mov: Move) -> Score {
+    let Some((mut gain, mut on_square, mut occupied)) = initial_exchange(board, mov) else {
+        return 0;
+    };
+
+    let mut gains = [0; MAX_EXCHANGES];
+    gains[0] = gain;
+
+    let mut attackers = attackers_to(board, mov.to, occupied);
+    let mut side = moving_color(board, mov).opposite();
+    let mut exchanges = 1;
+
+    while exchanges < MAX_EXCHANGES {
+        let Some((piece, square)) = least_valuable_attacker(board, attackers & occupied, side)
+        else {
+            break;
+        };
+
+        // the king can only capture if the other side has nothing left to capture it back with
+        if piece == Piece::King
+            && !(attackers & occupied & board.color_board(side.opposite())).is_empty()
+        {
+            break;
+        }
+
+        // capturing gains the piece on the square, but risks whatever was gained by the capture before it
+        gain = on_square.material_value() - gain;
+        gains[exchanges] = gain;
+        exchanges += 1;
+
+        occupied ^= Bitboard::shifted_board(square);
+        attackers |= xray_attackers(board, mov.to, occupied);
+        on_square = piece;
+        side = side.opposite();
+    }
+
+    // each side stops the exchange as soon as carrying on would be worse for it than stopping
+    for i in (1..exchanges).rev() {
+        gains[i - 1] = -Score::max(-gains[i - 1], gains[i]);
+    }
+
+    gains[0]
+}
+
+/// Checks whether the static exchange evaluation of a move is at least the given threshold
+///
+/// Gives the same answer as comparing `see` to the threshold, but stops as soon as the answer is known.
+pub fn see_ge(board: &Board, mov: Move, threshold: Score) -> bool {
+    let Some((gain, on_square, mut occupied)) = initial_exchange(board, mov) else {
+        return threshold <= 0;
+    };
+
+    // even if the moved piece is captured for free, the move is still good enough
+    let mut swap = gain - threshold;
+    if swap < 0 {
+        return false;
+    }
+
+    swap = on_square.material_value() - swap;
+    if swap <= 0 {
+        return true;
+    }
+
+    let mut attackers = attackers_to(board, mov.to, occupied);
+    let mut side = moving_color(board, mov);
+    let mut result = true;
+
+    loop {
+        side = side.opposite();
+
+        let Some((piece, square)) = least_valuable_attacker(board, attackers & occupied, side)
+        else {
+            break;
+        };
+
+        result = !result;
+
+        // a king capture is only possible if the other side can't capture back, which decides the exchange either way
+        if piece == Piece::King {
+            return match (attackers & occupied & board.color_board(side.opposite())).is_empty() {
+                true => result,
+                false => !result,
+            };
+        }
+
+        // the side capturing can stop here, the exchange can't turn back in favour of the other side
+        swap = piece.material_value() - swap;
+        if swap < result as Score {
+            break;
+        }
+
+        occupied ^= Bitboard::shifted_board(square);
+        attackers |= xray_attackers(board, mov.to, occupied);
+    }
+
+    result
+}
+
+/// Static exchange evaluation of capturing the piece on a square with the given color's least valuable attacker, as
+/// used by the evaluation to find pieces that can be won
+///
+/// Gives 0 if the square is empty or the color can't capture on it.
+pub fn see_capture(board: &Board, square: Square, color: Color) -> Score {
+    let Some(captured) = board.piece_at(square) else {
+        return 0;
+    };
+
+    let attackers = attackers_to(board, square, board.all_pieces());
+    let Some((piece, from)) = least_valuable_attacker(board, attackers, color) else {
+        return 0;
+    };
+
+    // a king that is the only attacker can't capture a defended piece
+    if piece == Piece::King && !(attackers & board.color_board(color.opposite())).is_empty() {
+        return 0;
+    }
+
+    let mov = Move {
+        from,
+        to: square,
+        piece,
+        flag: MoveFlag::Capture(captured),
+    };
+
+    see(board, mov)
+}
+
+/// Color of the piece making a move
+fn moving_color(board: &Board, mov: Move) -> Color {
+    match board.color_board(Color::White).bit_at(mov.from) {
+        true => Color::White,
+        false => Color::Black,
+    }
+}
+
+/// Material won by the move itself, the piece left standing on the target square and the occupancy after the move
+///
+/// Castling can't start an exchange, so `None` is given for it.
+fn initial_exchange(board: &Board, mov: Move) -> Option<(Score, Piece, Bitboard)> {
+    use MoveFlag::*;
+
+    let mut occupied = board.all_pieces() ^ Bitboard::shifted_board(mov.from);
+    occupied |= Bitboard::shifted_board(mov.to);
+
+    let promotion_gain = |promoted: Piece| promoted.material_value() - Piece::Pawn.material_value();
+
+    Some(match mov.flag {
+        Quiet | PawnDoubleMove(_) => (0, mov.piece, occupied),
+        Capture(captured) => (captured.material_value(), mov.piece, occupied),
+        Promotion(promoted) => (promotion_gain(promoted), promoted, occupied),
+        CapturePromotion(captured, promoted) => (
+            captured.material_value() + promotion_gain(promoted),
+            promoted,
+            occupied,
+        ),
+        EnPassantCapture(captured_square) => (
+            Piece::Pawn.material_value(),
+            Piece::Pawn,
+            occupied ^ Bitboard::shifted_board(captured_square),
+        ),
+        KingCastle | QueenCastle => return None,
+    })
+}
+
+/// Pieces of both colors attacking a square, with sliding pieces blocked by the given occupancy
+fn attackers_to(board: &Board, square: Square, occupied: Bitboard) -> Bitboard {
+    use Color::*;
+    use Piece::*;
+
+    (PAWN_ATTACKS[Black][square] & board.piece_board(Pawn, White))
+        | (PAWN_ATTACKS[White][square] & board.piece_board(Pawn, Black))
+        | (KNIGHT_MOVES[square] & pieces(board, Knight))
+        | (KING_MOVES[square] & pieces(board, King))
+        | xray_attackers(board, square, occupied)
+}
+
+/// Sliding pieces of both colors attacking a square, which can be revealed once the pieces in front of them capture
+fn xray_attackers(board: &Board, square: Square, occupied: Bitboard) -> Bitboard {
+    use Piece::*;
+
+    let queens = pieces(board, Queen);
+
+    ((BISHOP_MAGICS[square].get(occupied) & (pieces(board, Bishop) | queens))
+        | (ROOK_MAGICS[square].get(occupied) & (pieces(board, Rook) | queens)))
+        & occupied
+}
+
+/// Least valuable piece of a color among the attackers, along with its square
+fn least_valuable_attacker(
+    board: &Board,
+    attackers: Bitboard,
+    color: Color,
+) -> Option<(Piece, Square)> {
+    ALL_PIECES.into_iter().find_map(|piece| {
+        let attackers = attackers & board.piece_board(piece, color);
+        (!attackers.is_empty()).then(|| (piece, attackers.get_first_square()))
+    })
+}
+
+/// Pieces of the given type of both colors
+fn pieces(board: &Board, piece: Piece) -> Bitboard {
+    board.piece_board(piece, Color::White) | board.piece_board(piece, Color::Black)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Finds the move given in long algebraic notation and checks `see` against `see_ge` around its value
+    fn check_see(fen: &str, mov: &str, expected: Score) {
+        let board = Board::new(fen);
+        let mov = board.parse_move(mov).unwrap();
+
+        assert_eq!(see(&board, mov), expected, "{} {}", fen, mov);
+        assert!(see_ge(&board, mov, expected), "{} {}", fen, mov);
+        assert!(!see_ge(&board, mov, expected + 1), "{} {}", fen, mov);
+    }
+
+    #[test]
+    fn test_see() {
+        // undefended pawn taken for free
+        check_see(
+            "1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1",
+            "e1e5",
+            100,
+        );
+
+        // pawn defended by a pawn, taken with a rook
+        check_see(
+            "1k1r4/1pp4p/p4p2/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1",
+            "e1e5",
+            -400,
+        );
+
+        // knight takes a pawn defended by a knight, with a queen and rook lined up behind to take back on both sides
+        check_see(
+            "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+            "d3e5",
+            -200,
+        );
+
+        // rook x-rays through the rook in front of it after it captures
+        check_see("4k3/4r3/8/4p3/8/8/4R3/4RK2 w - - 0 1", "e2e5", 100);
+
+        // en passant and quiet moves onto attacked squares
+        check_see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 100);
+        check_see("4k3/8/2p5/8/8/8/8/3RK3 w - - 0 1", "d1d5", -500);
+        check_see("4k3/8/8/8/8/8/8/3RK3 w - - 0 1", "d1d5", 0);
+
+        // the king can only take back if the square isn't defended
+        check_see("4k3/8/8/8/8/3q4/8/3RK3 b - - 0 1", "d3d1", -400);
+        check_see("3rk3/8/8/8/8/3q4/8/3RK3 b - - 0 1", "d3d1", 500);
+        check_see("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1", "e1e2", 500);
+
+        // promotions gain the value of the new piece
+        check_see("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q", 1100);
+        check_see("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q", -100);
+    }
+
+    #[test]
+    fn test_see_capture() {
+        let (c6, d5, c3) = (18, 27, 42);
+
+        // either side can capture, starting with its least valuable attacker, whoever is to move
+        let board = Board::new("4k3/8/2p5/3n4/4P3/2N5/8/4K3 b - - 0 1");
+        assert_eq!(see_capture(&board, d5, Color::White), 300);
+        assert_eq!(see_capture(&board, c3, Color::Black), 300);
+
+        // empty squares and squares the color doesn't attack can't be captured on
+        assert_eq!(see_capture(&board, 0, Color::White), 0);
+        assert_eq!(see_capture(&board, c6, Color::White), 0);
+
+        // a king can only take a piece that isn't defended
+        let (d3, d2) = (43, 51);
+        assert_eq!(
+            see_capture(
+                &Board::new("4k3/8/8/8/8/3r4/4K3/8 w - - 0 1"),
+                d3,
+                Color::White
+            ),
+            500
+        );
+        assert_eq!(
+            see_capture(
+                &Board::new("4k3/8/8/8/8/3r4/3rK3/8 w - - 0 1"),
+                d2,
+                Color::White
+            ),
+            0
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use super::Score;
+use crate::{
+    board::Board,
+    core::{Color, Piece, NUM_PIECES},
+};
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// Phase of the game with all non-pawn pieces on the board, the phase counts down towards 0 as pieces are traded
+pub const MAX_PHASE: i32 = 24;
+
+/// Contribution of each piece to the game phase
+const PHASE_WEIGHTS: [i32; NUM_PIECES] = [0, 1, 1, 2, 4, 0];
+
+/// Pair of midgame and endgame scores, which are blended together based on the phase of the game
+///
+/// Arithmetic on tapered scores saturates instead of overflowing, since weights loaded from a file can be as large as
+/// a `Score` allows and many of them are added together.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Tapered {
+    pub mg: Score,
+    pub eg: Score,
+}
+
+impl Tapered {
+    pub const fn new(mg: Score, eg: Score) -> Tapered {
+        Tapered { mg, eg }
+    }
+
+    /// Linearly interpolates between the midgame (at `MAX_PHASE`) and endgame (at 0) scores
+    pub fn interpolate(self, phase: i32) -> Score {
+        let phase = phase.clamp(0, MAX_PHASE);
+        let blended = (self.mg as i32 * phase + self.eg as i32 * (MAX_PHASE - phase)) / MAX_PHASE;
+
+        blended as Score
+    }
+}
+
+impl Add for Tapered {
+    type Output = Tapered;
+
+    fn add(self, rhs: Tapered) -> Tapered {
+        Tapered::new(self.mg.saturating_add(rhs.mg), self.eg.saturating_add(rhs.eg))
+    }
+}
+
+impl AddAssign for Tapered {
+    fn add_assign(&mut self, rhs: Tapered) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Tapered {
+    type Output = Tapered;
+
+    fn sub(self, rhs: Tapered) -> Tapered {
+        Tapered::new(self.mg.saturating_sub(rhs.mg), self.eg.saturating_sub(rhs.eg))
+    }
+}
+
+impl SubAssign for Tapered {
+    fn sub_assign(&mut self, rhs: Tapered) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for Tapered {
+    type Output = Tapered;
+
+    fn neg(self) -> Tapered {
+        Tapered::new(self.mg.saturating_neg(), self.eg.saturating_neg())
+    }
+}
+
+impl Mul<Score> for Tapered {
+    type Output = Tapered;
+
+    fn mul(self, rhs: Score) -> Tapered {
+        Tapered::new(self.mg.saturating_mul(rhs), self.eg.saturating_mul(rhs))
+    }
+}
+
+/// Computes the phase of the game from the remaining non-pawn material, between 0 (endgame) and `MAX_PHASE` (opening)
+pub fn game_phase(board: &Board) -> i32 {
+    let phase: i32 = [Piece::Knight, Piece::Bishop, Piece::Rook, Piece::Queen]
+        .into_iter()
+        .map(|piece| {
+            let count = board.piece_board(piece, Color::White).count_bits()
+                + board.piece_board(piece, Color::Black).count_bits();
+
+            PHASE_WEIGHTS[piece as usize] * count as i32
+        })
+        .sum();
+
+    // early promotions can push the phase above the starting material
+    phase.min(MAX_PHASE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_game_phase() {
+        assert_eq!(game_phase(&Board::default()), MAX_PHASE);
+        assert_eq!(game_phase(&Board::new("4k3/pppp4/8/8/8/8/4PPPP/4K3 w - - 0 1")), 0);
+        assert_eq!(game_phase(&Board::new("3rk3/8/8/8/8/8/8/2N1K3 w - - 0 1")), 3);
+
+        let score = Tapered::new(100, -50);
+        assert_eq!(score.interpolate(MAX_PHASE), 100);
+        assert_eq!(score.interpolate(0), -50);
+        assert_eq!(score.interpolate(MAX_PHASE / 2), 25);
+    }
+}
@@ -29,7 +29,7 @@ impl<D> TranspositionTable<D>
 where
     D: Copy + Default,
 {
-    /// Generates an empty transposition table with alloted size in MB
+    /// Generates an empty transposition table with alloted size in MB, a size of 0 gives a table that stores nothing
     pub fn new(mb: usize) -> TranspositionTable<D> {
         // calculate how many entries can be stored in the table
         let capacity = (mb * MB_SIZE) / size_of::<Entry<D>>();
@@ -52,6 +52,10 @@ where
 
     /// Inserts data into the transposition table
     pub fn insert(&mut self, hash: ZobristHash, data: D) {
+        if self.capacity == 0 {
+            return;
+        }
+
         let index = self.hash_index(hash);
 
         let residing_hash = self.table[index].hash;
@@ -69,6 +73,10 @@ where
     ///
     /// The depth is needed to prevent cases where a shallow evaluation is used instead of a deeper and more accurate evaluation
     pub fn get(&mut self, hash: ZobristHash) -> Option<D> {
+        if self.capacity == 0 {
+            return None;
+        }
+
         let entry = self.table[self.hash_index(hash)];
 
         self.total += 1;
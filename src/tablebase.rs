@@ -0,0 +1,481 @@
+//! Endgame tablebases, generated by retrograde analysis and probed during the search
+//!
+//! Tables are indexed by the square of every piece and the side to move, so a table holds 2·64^n entries for n
+//! pieces. No symmetry is used to reduce this, so tables are up to 8 times larger than they need to be without pawns
+//! (mirroring files, ranks and the diagonal), and 2 times larger with pawns (mirroring files). Compression keeps the
+//! files small, but generating a table needs all of its entries in memory, which is why tables stop at `MAX_PIECES`.
+
+use crate::board::Board;
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+    time::Instant,
+};
+
+mod generator;
+mod position;
+
+pub use position::MAX_PIECES;
+
+use generator::generate_table;
+use position::{Signature, TbPosition};
+
+/// Identifies tablebase files, followed by a format version
+const FILE_MAGIC: &[u8; 4] = b"OTB\x01";
+
+/// File extension used for tablebase files
+const FILE_EXTENSION: &str = "otb";
+
+/// Amount of entries per compressed block, a probe only has to decompress the block its entry is in
+const BLOCK_SIZE: usize = 4096;
+
+/// Result of a tablebase position from the side to move's point of view
+///
+/// Wins and losses store the distance to mate in plies
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Outcome {
+    Win(u8),
+    Draw,
+    Loss(u8),
+}
+
+impl Outcome {
+    /// Encoded value of positions that cannot occur in a game
+    const ILLEGAL: u8 = 255;
+
+    /// Largest distance to mate that can be encoded
+    const MAX_PLIES: u8 = 124;
+
+    /// Encodes the outcome into a single byte: 0 is a draw, 1-127 is a win and 128-252 is a loss
+    fn encode(self) -> u8 {
+        match self {
+            Outcome::Draw => 0,
+            Outcome::Win(plies) => {
+                assert!(
+                    plies <= Self::MAX_PLIES,
+                    "distance to mate is too long to encode"
+                );
+                plies
+            }
+            Outcome::Loss(plies) => {
+                assert!(
+                    plies <= Self::MAX_PLIES,
+                    "distance to mate is too long to encode"
+                );
+                128 + plies
+            }
+        }
+    }
+
+    /// Decodes a single byte into an outcome, returning `None` for illegal or other special values
+    fn decode(value: u8) -> Option<Outcome> {
+        match value {
+            0 => Some(Outcome::Draw),
+            1..=127 => Some(Outcome::Win(value)),
+            128..=252 => Some(Outcome::Loss(value - 128)),
+            _ => None,
+        }
+    }
+
+    /// Converts the outcome of a position into the outcome for the side that moved into it
+    fn parent(self) -> Outcome {
+        match self {
+            Outcome::Win(plies) => Outcome::Loss(plies + 1),
+            Outcome::Draw => Outcome::Draw,
+            Outcome::Loss(plies) => Outcome::Win(plies + 1),
+        }
+    }
+
+    /// Returns the better of two outcomes, preferring faster wins and slower losses
+    fn max(self, other: Outcome) -> Outcome {
+        let rank = |outcome: Outcome| match outcome {
+            Outcome::Win(plies) => 1000 - plies as i32,
+            Outcome::Draw => 0,
+            Outcome::Loss(plies) => -1000 + plies as i32,
+        };
+
+        match rank(self) >= rank(other) {
+            true => self,
+            false => other,
+        }
+    }
+}
+
+/// Run-length encoded table of outcomes, split into blocks that can be decoded independently
+#[derive(Clone)]
+pub struct Table {
+    len: usize,
+    offsets: Vec<u32>, // start of each block within the data, with an extra final offset for the end of the data
+    data: Vec<u8>,     // pairs of (run length, value)
+}
+
+impl Table {
+    /// Compresses a table of encoded outcomes
+    fn compress(values: &[u8]) -> Table {
+        let mut offsets = Vec::new();
+        let mut data = Vec::new();
+
+        for block in values.chunks(BLOCK_SIZE) {
+            offsets.push(data.len() as u32);
+
+            let mut run: Option<(u8, u8)> = None;
+
+            for value in block {
+                run = match run {
+                    Some((length, run_value)) if run_value == *value && length < u8::MAX => {
+                        Some((length + 1, run_value))
+                    }
+                    Some((length, run_value)) => {
+                        data.extend([length, run_value]);
+                        Some((1, *value))
+                    }
+                    None => Some((1, *value)),
+                };
+            }
+
+            if let Some((length, run_value)) = run {
+                data.extend([length, run_value]);
+            }
+        }
+
+        offsets.push(data.len() as u32);
+
+        Table {
+            len: values.len(),
+            offsets,
+            data,
+        }
+    }
+
+    /// Fetches a single encoded outcome from the table
+    fn get(&self, index: usize) -> u8 {
+        let block = index / BLOCK_SIZE;
+        let mut remaining = index % BLOCK_SIZE;
+
+        let start = self.offsets[block] as usize;
+        let end = self.offsets[block + 1] as usize;
+
+        for run in self.data[start..end].chunks(2) {
+            let length = run[0] as usize;
+
+            if remaining < length {
+                return run[1];
+            }
+
+            remaining -= length;
+        }
+
+        unreachable!("index is outside of the table")
+    }
+
+    /// Expands the entire table back into one encoded outcome per entry
+    fn decompress(&self) -> Vec<u8> {
+        let mut values = Vec::with_capacity(self.len);
+
+        for run in self.data.chunks(2) {
+            values.extend(std::iter::repeat_n(run[1], run[0] as usize));
+        }
+
+        values
+    }
+
+    /// Serializes the table in the form: magic, entry count, block count, block offsets, data (all integers little endian)
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = FILE_MAGIC.to_vec();
+
+        bytes.extend((self.len as u32).to_le_bytes());
+        bytes.extend((self.offsets.len() as u32 - 1).to_le_bytes());
+
+        for offset in self.offsets.iter() {
+            bytes.extend(offset.to_le_bytes());
+        }
+
+        bytes.extend(self.data.iter());
+        bytes
+    }
+
+    /// Deserializes a table, checking that it has the size expected for its signature
+    fn from_bytes(bytes: &[u8], expected_len: usize) -> io::Result<Table> {
+        let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message.to_string());
+
+        let read_u32 = |at: usize| -> io::Result<u32> {
+            let slice = bytes
+                .get(at..at + 4)
+                .ok_or_else(|| invalid("file is truncated"))?;
+            Ok(u32::from_le_bytes(slice.try_into().unwrap()))
+        };
+
+        if bytes.get(0..4) != Some(FILE_MAGIC) {
+            return Err(invalid("not an otter tablebase file"));
+        }
+
+        let len = read_u32(4)? as usize;
+        let blocks = read_u32(8)? as usize;
+
+        if len != expected_len || blocks != len.div_ceil(BLOCK_SIZE) {
+            return Err(invalid("table size does not match its signature"));
+        }
+
+        let offsets = (0..=blocks)
+            .map(|block| read_u32(12 + 4 * block))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let data = bytes[12 + 4 * (blocks + 1)..].to_vec();
+
+        if *offsets.last().unwrap() as usize != data.len() {
+            return Err(invalid("file is truncated"));
+        }
+
+        Ok(Table { len, offsets, data })
+    }
+}
+
+/// Collection of endgame tables, giving perfect results for positions with few pieces
+#[derive(Clone, Default)]
+pub struct Tablebase {
+    tables: HashMap<Signature, Table>,
+}
+
+impl Tablebase {
+    /// Loads every tablebase file within the given directory, skipping (and reporting) any invalid files
+    pub fn load(directory: &str) -> Tablebase {
+        let mut tablebase = Tablebase::default();
+
+        let entries = match fs::read_dir(directory) {
+            Ok(entries) => entries,
+            Err(_) => return tablebase, // no tablebases available
+        };
+
+        for path in entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+        {
+            if path.extension().and_then(|e| e.to_str()) != Some(FILE_EXTENSION) {
+                continue;
+            }
+
+            let signature = match path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(Signature::from_name)
+            {
+                Some(signature) => signature,
+                None => continue,
+            };
+
+            match fs::read(&path)
+                .and_then(|bytes| Table::from_bytes(&bytes, signature.table_size()))
+            {
+                Ok(table) => {
+                    tablebase.tables.insert(signature, table);
+                }
+                Err(e) => println!("Unable to load tablebase {}: {}", path.display(), e),
+            }
+        }
+
+        tablebase
+    }
+
+    /// Generates all tables with up to `max_pieces` pieces, printing progress along the way
+    pub fn generate(max_pieces: usize) -> Tablebase {
+        let mut tablebase = Tablebase::default();
+
+        for signature in Signature::all(max_pieces) {
+            let start = Instant::now();
+            let values = generate_table(&signature, &tablebase);
+
+            println!(
+                "Generated {} in {:.1}s",
+                signature,
+                start.elapsed().as_secs_f32()
+            );
+
+            tablebase.tables.insert(signature, Table::compress(&values));
+        }
+
+        tablebase
+    }
+
+    /// Writes every table into the given directory as `[signature].otb`
+    pub fn save(&self, directory: &str) -> io::Result<()> {
+        fs::create_dir_all(directory)?;
+
+        for (signature, table) in self.tables.iter() {
+            let path = Path::new(directory).join(format!("{}.{}", signature, FILE_EXTENSION));
+            fs::write(path, table.to_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if no tables are available
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    fn table(&self, signature: &Signature) -> Option<&Table> {
+        self.tables.get(signature)
+    }
+
+    /// Looks up the outcome of the given board position, if a table is available for it
+    ///
+    /// Positions with castling rights or an en passant square are not covered by the tables
+    pub fn probe(&self, board: &Board) -> Option<Outcome> {
+        if self.is_empty() || board.any_castle_rights() || !board.en_passant_board().is_empty() {
+            return None;
+        }
+
+        let (signature, position) = TbPosition::from_board(board)?;
+        let table = self.table(&signature)?;
+
+        Outcome::decode(table.get(position.index()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{board::Move, core::Piece, search::Searcher};
+    use std::sync::OnceLock;
+
+    /// Small tables generated once in memory and shared between tests, as generation is slow in debug builds
+    fn tablebase() -> &'static Tablebase {
+        static TABLEBASE: OnceLock<Tablebase> = OnceLock::new();
+
+        TABLEBASE.get_or_init(|| {
+            let mut tablebase = Tablebase::default();
+
+            for name in ["KQvK", "KRvK", "KBvK", "KNvK", "KPvK", "KRvKN"] {
+                let signature = Signature::from_name(name).unwrap();
+                let values = generate_table(&signature, &tablebase);
+                tablebase.tables.insert(signature, Table::compress(&values));
+            }
+
+            tablebase
+        })
+    }
+
+    /// Longest distance to mate of any position in a table
+    fn longest_win(name: &str) -> u8 {
+        let table = tablebase()
+            .table(&Signature::from_name(name).unwrap())
+            .unwrap();
+
+        table
+            .decompress()
+            .into_iter()
+            .filter_map(|value| match Outcome::decode(value) {
+                Some(Outcome::Win(plies)) => Some(plies),
+                _ => None,
+            })
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_longest_mates() {
+        // KQK is mate in 10 moves at most and KRK is mate in 16, counted here in plies
+        assert_eq!(longest_win("KQvK"), 19);
+        assert_eq!(longest_win("KRvK"), 31);
+    }
+
+    #[test]
+    fn test_probe_four_pieces() {
+        let probe = |fen: &str| tablebase().probe(&Board::new(fen));
+
+        // the rook mates on the back rank, or is taken by the king leaving a bare knight
+        assert_eq!(
+            probe("k7/8/1K6/8/8/8/8/3R3n w - - 0 1"),
+            Some(Outcome::Win(1))
+        );
+        assert_eq!(probe("6R1/7k/8/8/8/8/8/K6n b - - 0 1"), Some(Outcome::Draw));
+
+        // the knight forks king and rook
+        assert_eq!(
+            probe("8/8/8/8/7k/4n3/8/K3R3 b - - 0 1"),
+            Some(Outcome::Draw)
+        );
+    }
+
+    #[test]
+    fn test_probe_pawn_endings() {
+        let probe = |fen: &str| tablebase().probe(&Board::new(fen));
+
+        // king in front of its pawn on the sixth rank wins regardless of the side to move
+        assert!(matches!(
+            probe("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"),
+            Some(Outcome::Win(_))
+        ));
+        assert!(matches!(
+            probe("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"),
+            Some(Outcome::Loss(_))
+        ));
+
+        // stalemate, and a defending king in front of the pawn
+        assert_eq!(
+            probe("4k3/4P3/4K3/8/8/8/8/8 b - - 0 1"),
+            Some(Outcome::Draw)
+        );
+        assert_eq!(
+            probe("8/8/8/8/8/4k3/4P3/4K3 w - - 0 1"),
+            Some(Outcome::Draw)
+        );
+
+        // same positions with colors swapped are looked up through the mirrored table
+        assert!(matches!(
+            probe("8/8/8/8/4p3/4k3/8/4K3 w - - 0 1"),
+            Some(Outcome::Loss(_))
+        ));
+
+        // tables don't cover positions with en passant squares
+        assert_eq!(probe("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1"), None);
+    }
+
+    #[test]
+    fn test_root_tablebase_moves() {
+        let mut searcher = Searcher::new(1);
+        searcher.set_tablebase(tablebase().clone());
+
+        // a king move mates fastest, but close to the 50 move limit the pawn has to move to keep the win
+        let mut board = Board::new("8/8/1k6/3KP3/8/8/8/8 w - - 0 1");
+        assert_eq!(searcher.best_move(&mut board).unwrap().0.piece, Piece::King);
+
+        let mut board = Board::new("8/8/1k6/3KP3/8/8/8/8 w - - 90 1");
+        assert_eq!(searcher.best_move(&mut board).unwrap().0.piece, Piece::Pawn);
+
+        // going back and forth brings up the same position again, where the fastest mate would now repeat
+        let mut board = Board::new("8/8/8/3k4/8/8/8/Q3K3 w - - 0 1");
+        let (fastest, _) = searcher.best_move(&mut board).unwrap();
+
+        board.make_move(fastest);
+        let reply = board.generate_moves()[0];
+        board.make_move(reply);
+
+        for mov in [
+            Move { from: fastest.to, to: fastest.from, ..fastest },
+            Move { from: reply.to, to: reply.from, ..reply },
+        ] {
+            board.make_move(board.parse_move(&mov.to_string()).unwrap());
+        }
+
+        let (mov, score) = searcher.best_move(&mut board).unwrap();
+        assert_ne!(mov, fastest);
+        assert!(score > 0);
+    }
+
+    #[test]
+    fn test_table_round_trip() {
+        let table = tablebase()
+            .table(&Signature::from_name("KQvK").unwrap())
+            .unwrap();
+
+        let loaded = Table::from_bytes(&table.to_bytes(), table.len).unwrap();
+        assert_eq!(loaded.decompress(), table.decompress());
+
+        for index in (0..table.len).step_by(997) {
+            assert_eq!(loaded.get(index), table.get(index));
+        }
+    }
+}
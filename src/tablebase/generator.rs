@@ -0,0 +1,207 @@
+use super::{
+    position::{Signature, TbPosition},
+    Outcome, Tablebase,
+};
+use std::{collections::HashMap, thread};
+
+// encoded values only used while generating
+const UNKNOWN: u8 = 254;
+const NO_EXIT: u8 = 253;
+
+/// Builds the table for a single signature using retrograde analysis
+///
+/// Every table reachable from this one through captures or promotions must already be in the tablebase
+pub fn generate_table(signature: &Signature, tablebase: &Tablebase) -> Vec<u8> {
+    let layout = signature.layout();
+    let size = signature.table_size();
+
+    // decompress the tables that can be reached with a capture or promotion, they are probed for every exit move
+    let children = load_children(signature, tablebase);
+
+    let mut values = vec![UNKNOWN; size];
+    let mut counters = vec![0u8; size];
+    let mut exits = vec![NO_EXIT; size];
+
+    // first pass (split across threads): find illegal positions, mates, and the best result of moves leaving the table
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = size.div_ceil(threads);
+
+    thread::scope(|scope| {
+        let chunks = values
+            .chunks_mut(chunk_size)
+            .zip(counters.chunks_mut(chunk_size))
+            .zip(exits.chunks_mut(chunk_size))
+            .enumerate();
+
+        for (chunk, ((values, counters), exits)) in chunks {
+            let layout = &layout;
+            let children = &children;
+
+            scope.spawn(move || {
+                for i in 0..values.len() {
+                    let index = chunk * chunk_size + i;
+                    let position = TbPosition::from_index(layout, index);
+
+                    (values[i], counters[i], exits[i]) = initial_entry(&position, children);
+                }
+            });
+        }
+    });
+
+    // positions that lose no matter what once all in-table moves are known to lose, or are only lost through exits
+    let mut horizon = 0;
+
+    for index in 0..size {
+        if values[index] == UNKNOWN && counters[index] == 0 {
+            if let Some(Outcome::Loss(plies)) = Outcome::decode(exits[index]) {
+                values[index] = Outcome::Loss(plies).encode();
+            }
+        }
+
+        if let Some(Outcome::Win(plies) | Outcome::Loss(plies)) = Outcome::decode(values[index]) {
+            horizon = horizon.max(plies);
+        }
+
+        if let Some(Outcome::Win(plies)) = Outcome::decode(exits[index]) {
+            horizon = horizon.max(plies);
+        }
+    }
+
+    // retrograde step: resolve positions in order of increasing distance to mate
+    let mut plies = 0;
+    while plies <= horizon {
+        // wins take at least one ply, so there is no win at the first level (and its encoding would be a draw)
+        let win = match plies {
+            0 => None,
+            _ => Some(Outcome::Win(plies).encode()),
+        };
+        let loss = Outcome::Loss(plies).encode();
+
+        // wins through exit moves are only final once no shorter in-table win was found
+        for index in 0..size {
+            if values[index] == UNKNOWN && Some(exits[index]) == win {
+                values[index] = exits[index];
+            }
+        }
+
+        for index in 0..size {
+            let value = values[index];
+
+            if Some(value) != win && value != loss {
+                continue;
+            }
+
+            let position = TbPosition::from_index(&layout, index);
+
+            for previous in position.predecessors() {
+                if values[previous] != UNKNOWN {
+                    continue;
+                }
+
+                if value == loss {
+                    // the previous side can move into this lost position and win
+                    values[previous] = Outcome::Win(plies + 1).encode();
+                    horizon = horizon.max(plies + 1);
+                } else {
+                    counters[previous] -= 1;
+
+                    // once every in-table move leads to a loss, the position is lost unless an exit saves it
+                    if counters[previous] == 0 {
+                        let lost_in = match Outcome::decode(exits[previous]) {
+                            Some(Outcome::Loss(exit_plies)) => Some(exit_plies.max(plies + 1)),
+                            None => Some(plies + 1),
+                            _ => None,
+                        };
+
+                        if let Some(lost_in) = lost_in {
+                            values[previous] = Outcome::Loss(lost_in).encode();
+                            horizon = horizon.max(lost_in);
+                        }
+                    }
+                }
+            }
+        }
+
+        plies += 1;
+    }
+
+    // anything left unresolved can be held by both sides
+    for value in values.iter_mut() {
+        if *value == UNKNOWN {
+            *value = Outcome::Draw.encode();
+        }
+    }
+
+    values
+}
+
+/// Finds the starting value, number of in-table moves and best exit move result of a position
+fn initial_entry(position: &TbPosition, children: &HashMap<Signature, Vec<u8>>) -> (u8, u8, u8) {
+    if !position.is_legal() {
+        return (Outcome::ILLEGAL, 0, NO_EXIT);
+    }
+
+    let moves = position.generate_moves();
+
+    if moves.is_empty() {
+        let outcome = match position.in_check() {
+            true => Outcome::Loss(0),
+            false => Outcome::Draw,
+        };
+
+        return (outcome.encode(), 0, NO_EXIT);
+    }
+
+    let mut counter = 0;
+    let mut best_exit: Option<Outcome> = None;
+
+    for mov in moves {
+        if !mov.is_exit() {
+            counter += 1;
+            continue;
+        }
+
+        // look up the resulting position, where only kings left is a draw
+        let outcome = match position.make_move(mov).normalize() {
+            Some((signature, child)) => {
+                let value = children[&signature][child.index()];
+                Outcome::decode(value).unwrap().parent()
+            }
+            None => Outcome::Draw,
+        };
+
+        best_exit = Some(match best_exit {
+            Some(best) => best.max(outcome),
+            None => outcome,
+        });
+    }
+
+    let exit = best_exit.map_or(NO_EXIT, Outcome::encode);
+
+    // with no in-table moves, the exits alone decide a draw (wins and losses are resolved later by distance)
+    let value = match (counter, best_exit) {
+        (0, Some(Outcome::Draw)) => Outcome::Draw.encode(),
+        _ => UNKNOWN,
+    };
+
+    (value, counter, exit)
+}
+
+/// Decompresses every table reachable from the given signature by a single capture or promotion
+fn load_children(signature: &Signature, tablebase: &Tablebase) -> HashMap<Signature, Vec<u8>> {
+    let mut children = HashMap::new();
+
+    for signature_child in signature.children() {
+        if children.contains_key(&signature_child) {
+            continue;
+        }
+
+        let table = tablebase.table(&signature_child).unwrap_or_else(|| {
+            panic!("{} must be generated before {}", signature_child, signature)
+        });
+
+        children.insert(signature_child, table.decompress());
+    }
+
+    children
+}
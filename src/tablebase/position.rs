@@ -0,0 +1,595 @@
+use crate::{
+    board::{Board, BISHOP_MAGICS, KING_MOVES, KNIGHT_MOVES, PAWN_ATTACKS, ROOK_MAGICS},
+    core::{Bitboard, Color, Piece, Square, ALL_PIECES, BOARD_SIZE, PROMOTION_PIECES},
+};
+use std::fmt::Display;
+
+/// Maximum number of pieces (including kings) that tablebases are built for
+pub const MAX_PIECES: usize = 4;
+
+/// Describes the material of a tablebase, listing the non-king pieces of each side from most to least valuable
+///
+/// Signatures are normalized so that white is always the side with the stronger material
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Signature {
+    white: Vec<Piece>,
+    black: Vec<Piece>,
+}
+
+impl Signature {
+    /// Builds a normalized signature from each side's non-king pieces, returning whether the colors had to be swapped
+    pub fn new(mut white: Vec<Piece>, mut black: Vec<Piece>) -> (Signature, bool) {
+        // most valuable pieces first, which also orders the pieces within the table index
+        white.sort_by_key(|piece| std::cmp::Reverse(*piece as usize));
+        black.sort_by_key(|piece| std::cmp::Reverse(*piece as usize));
+
+        let strength = |pieces: &Vec<Piece>| {
+            (
+                pieces.len(),
+                pieces.iter().map(|p| *p as usize).collect::<Vec<_>>(),
+            )
+        };
+
+        match strength(&white) >= strength(&black) {
+            true => (Signature { white, black }, false),
+            false => (
+                Signature {
+                    white: black,
+                    black: white,
+                },
+                true,
+            ),
+        }
+    }
+
+    /// Parses a signature name such as `KRvKN`
+    pub fn from_name(name: &str) -> Option<Signature> {
+        let (white, black) = name.split_once('v')?;
+
+        let parse_side = |side: &str| -> Option<Vec<Piece>> {
+            let mut chars = side.chars();
+
+            if chars.next()? != 'K' {
+                return None;
+            }
+
+            chars
+                .map(|c| match "PNBRQ".contains(c) {
+                    true => Some(Piece::from(c)),
+                    false => None,
+                })
+                .collect()
+        };
+
+        let (signature, _) = Self::new(parse_side(white)?, parse_side(black)?);
+        Some(signature)
+    }
+
+    /// Lists every normalized signature with at least one non-king piece and up to `max_pieces` pieces in total,
+    /// ordered so that any table only depends on tables earlier in the list
+    pub fn all(max_pieces: usize) -> Vec<Signature> {
+        let extras = [
+            Piece::Queen,
+            Piece::Rook,
+            Piece::Bishop,
+            Piece::Knight,
+            Piece::Pawn,
+        ];
+
+        // build up every multiset of non-king pieces for a single side
+        let mut sides: Vec<Vec<Piece>> = vec![Vec::new()];
+        for count in 1..=max_pieces - 2 {
+            let mut next = Vec::new();
+
+            for side in sides.iter().filter(|side| side.len() == count - 1) {
+                for piece in extras {
+                    // only add pieces in descending order to avoid duplicates
+                    if side
+                        .last()
+                        .is_none_or(|last| *last as usize >= piece as usize)
+                    {
+                        let mut new_side = side.clone();
+                        new_side.push(piece);
+                        next.push(new_side);
+                    }
+                }
+            }
+
+            sides.extend(next);
+        }
+
+        let mut signatures = Vec::new();
+
+        for white in sides.iter() {
+            for black in sides.iter() {
+                if white.is_empty() || white.len() + black.len() + 2 > max_pieces {
+                    continue;
+                }
+
+                let (signature, _) = Self::new(white.clone(), black.clone());
+                if !signatures.contains(&signature) {
+                    signatures.push(signature);
+                }
+            }
+        }
+
+        // captures lower the piece count and promotions lower the pawn count, so order by both
+        signatures.sort_by_key(|signature| (signature.piece_count(), signature.pawn_count()));
+        signatures
+    }
+
+    /// Lists the signatures reachable from this one with a single capture, promotion or capturing promotion
+    pub fn children(&self) -> Vec<Signature> {
+        let mut children = Vec::new();
+
+        for (own, other) in [(&self.white, &self.black), (&self.black, &self.white)] {
+            // each option replaces (or keeps) a pawn of the moving side and removes (or keeps) an opposing piece
+            let mut promotions = vec![None];
+            if own.contains(&Piece::Pawn) {
+                promotions.extend(PROMOTION_PIECES.map(Some));
+            }
+
+            let mut captures = vec![None];
+            captures.extend((0..other.len()).map(Some));
+
+            for promotion in promotions.iter() {
+                for capture in captures.iter() {
+                    if promotion.is_none() && capture.is_none() {
+                        continue;
+                    }
+
+                    let mut own = own.clone();
+                    let mut other = other.clone();
+
+                    if let Some(promoted) = promotion {
+                        let pawn = own.iter().position(|p| *p == Piece::Pawn).unwrap();
+                        own[pawn] = *promoted;
+                    }
+
+                    if let Some(captured) = capture {
+                        other.remove(*captured);
+                    }
+
+                    // positions with only kings remaining are always drawn
+                    if own.is_empty() && other.is_empty() {
+                        continue;
+                    }
+
+                    let (child, _) = Signature::new(own, other);
+                    if !children.contains(&child) {
+                        children.push(child);
+                    }
+                }
+            }
+        }
+
+        children
+    }
+
+    /// Total number of pieces in this signature, including kings
+    pub fn piece_count(&self) -> usize {
+        self.white.len() + self.black.len() + 2
+    }
+
+    /// Number of pawns of either color in this signature
+    fn pawn_count(&self) -> usize {
+        self.white
+            .iter()
+            .chain(self.black.iter())
+            .filter(|piece| **piece == Piece::Pawn)
+            .count()
+    }
+
+    /// Amount of entries in a table for this signature, covering every placement of pieces and both sides to move
+    pub fn table_size(&self) -> usize {
+        2 * BOARD_SIZE.pow(self.piece_count() as u32)
+    }
+
+    /// Piece and color of each slot of the table index, in the order they are indexed
+    pub fn layout(&self) -> Vec<(Piece, Color)> {
+        let mut layout = vec![(Piece::King, Color::White)];
+        layout.extend(self.white.iter().map(|piece| (*piece, Color::White)));
+        layout.push((Piece::King, Color::Black));
+        layout.extend(self.black.iter().map(|piece| (*piece, Color::Black)));
+        layout
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let side = |pieces: &Vec<Piece>| pieces.iter().map(|p| char::from(*p)).collect::<String>();
+        write!(f, "K{}vK{}", side(&self.white), side(&self.black))
+    }
+}
+
+/// Pieces a pawn can promote to, in the form used by tablebase moves
+const PROMOTIONS: [Option<Piece>; 4] = [
+    Some(PROMOTION_PIECES[0]),
+    Some(PROMOTION_PIECES[1]),
+    Some(PROMOTION_PIECES[2]),
+    Some(PROMOTION_PIECES[3]),
+];
+
+/// A single piece within a tablebase position
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TbPiece {
+    pub piece: Piece,
+    pub color: Color,
+    pub square: Square,
+}
+
+/// Lightweight position representation used for generating and probing tablebases
+///
+/// Pieces are stored in the order of their signature's layout, castling and en passant are never possible
+#[derive(Clone, Copy, Debug)]
+pub struct TbPosition {
+    pieces: [TbPiece; MAX_PIECES],
+    len: usize,
+    pub active: Color,
+}
+
+/// A tablebase move, along with whether it leaves the current table by capturing or promoting
+#[derive(Clone, Copy)]
+pub struct TbMove {
+    pub slot: usize,
+    pub to: Square,
+    pub promotion: Option<Piece>,
+    pub capture: Option<usize>,
+}
+
+impl TbMove {
+    /// Returns true if the resulting position belongs to a different signature
+    pub fn is_exit(self) -> bool {
+        self.promotion.is_some() || self.capture.is_some()
+    }
+}
+
+impl TbPosition {
+    /// Decodes a table index into a position, given the signature's layout
+    pub fn from_index(layout: &[(Piece, Color)], mut index: usize) -> TbPosition {
+        let active = match index % 2 {
+            0 => Color::White,
+            _ => Color::Black,
+        };
+        index /= 2;
+
+        let mut position = TbPosition::empty(active);
+
+        for (piece, color) in layout {
+            position.push(TbPiece {
+                piece: *piece,
+                color: *color,
+                square: index % BOARD_SIZE,
+            });
+            index /= BOARD_SIZE;
+        }
+
+        position
+    }
+
+    /// Encodes this position into an index of its table, assuming pieces are in layout order
+    pub fn index(&self) -> usize {
+        let mut index = 0;
+
+        for piece in self.pieces().iter().rev() {
+            index = index * BOARD_SIZE + piece.square;
+        }
+
+        2 * index + self.active as usize
+    }
+
+    /// Converts a board into a normalized position along with its signature, if it has few enough pieces
+    ///
+    /// If the stronger side is black, colors are swapped and ranks are mirrored so the position fits a white-stronger table
+    pub fn from_board(board: &Board) -> Option<(Signature, TbPosition)> {
+        if board.all_pieces().count_bits() > MAX_PIECES {
+            return None;
+        }
+
+        let mut position = TbPosition::empty(board.active_color());
+
+        for color in [Color::White, Color::Black] {
+            for piece in ALL_PIECES {
+                for square in board.piece_board(piece, color) {
+                    position.push(TbPiece {
+                        piece,
+                        color,
+                        square,
+                    });
+                }
+            }
+        }
+
+        position.normalize()
+    }
+
+    /// Finds the signature of this position and re-orders (and possibly color flips) its pieces to match the table layout
+    pub fn normalize(&self) -> Option<(Signature, TbPosition)> {
+        let side = |color: Color| {
+            self.pieces()
+                .iter()
+                .filter(|p| p.color == color && p.piece != Piece::King)
+                .map(|p| p.piece)
+                .collect::<Vec<_>>()
+        };
+
+        let white = side(Color::White);
+        let black = side(Color::Black);
+
+        // kings alone cannot be stored in a table
+        if white.is_empty() && black.is_empty() {
+            return None;
+        }
+
+        let (signature, flipped) = Signature::new(white, black);
+
+        let mut remaining = self.pieces().to_vec();
+
+        if flipped {
+            for p in remaining.iter_mut() {
+                p.color = p.color.opposite();
+                p.square ^= 56; // mirrors the rank of the square
+            }
+        }
+
+        let mut position = TbPosition::empty(match flipped {
+            true => self.active.opposite(),
+            false => self.active,
+        });
+
+        // fill each slot of the layout with a matching piece
+        for (piece, color) in signature.layout() {
+            let found = remaining
+                .iter()
+                .position(|p| p.piece == piece && p.color == color)?;
+
+            position.push(remaining.swap_remove(found));
+        }
+
+        Some((signature, position))
+    }
+
+    fn empty(active: Color) -> TbPosition {
+        TbPosition {
+            pieces: [TbPiece {
+                piece: Piece::King,
+                color: Color::White,
+                square: 0,
+            }; MAX_PIECES],
+            len: 0,
+            active,
+        }
+    }
+
+    fn push(&mut self, piece: TbPiece) {
+        self.pieces[self.len] = piece;
+        self.len += 1;
+    }
+
+    pub fn pieces(&self) -> &[TbPiece] {
+        &self.pieces[..self.len]
+    }
+
+    fn occupied(&self) -> Bitboard {
+        let mut board = Bitboard::EMPTY;
+
+        for p in self.pieces() {
+            board.set_bit_at(p.square, true);
+        }
+
+        board
+    }
+
+    fn color_occupied(&self, color: Color) -> Bitboard {
+        let mut board = Bitboard::EMPTY;
+
+        for p in self.pieces().iter().filter(|p| p.color == color) {
+            board.set_bit_at(p.square, true);
+        }
+
+        board
+    }
+
+    fn king_square(&self, color: Color) -> Square {
+        self.pieces()
+            .iter()
+            .find(|p| p.piece == Piece::King && p.color == color)
+            .unwrap()
+            .square
+    }
+
+    /// Squares attacked by a single piece, given the occupied squares of the board
+    fn attacks(piece: TbPiece, occupied: Bitboard) -> Bitboard {
+        use Piece::*;
+
+        match piece.piece {
+            Pawn => PAWN_ATTACKS[piece.color][piece.square],
+            Knight => KNIGHT_MOVES[piece.square],
+            Bishop => BISHOP_MAGICS[piece.square].get(occupied),
+            Rook => ROOK_MAGICS[piece.square].get(occupied),
+            Queen => {
+                BISHOP_MAGICS[piece.square].get(occupied) | ROOK_MAGICS[piece.square].get(occupied)
+            }
+            King => KING_MOVES[piece.square],
+        }
+    }
+
+    /// Returns true if the given square is attacked by any piece of the given color
+    fn is_attacked(&self, square: Square, by: Color) -> bool {
+        let occupied = self.occupied();
+
+        self.pieces()
+            .iter()
+            .filter(|p| p.color == by)
+            .any(|p| Self::attacks(*p, occupied).bit_at(square))
+    }
+
+    /// Checks that no pieces overlap, no pawns are on a back rank and the side not to move is not in check
+    pub fn is_legal(&self) -> bool {
+        let pieces = self.pieces();
+
+        for (i, a) in pieces.iter().enumerate() {
+            if pieces[i + 1..].iter().any(|b| a.square == b.square) {
+                return false;
+            }
+
+            if a.piece == Piece::Pawn && (a.square < 8 || a.square >= 56) {
+                return false;
+            }
+        }
+
+        !self.is_attacked(self.king_square(self.active.opposite()), self.active)
+    }
+
+    /// Returns true if the side to move is in check
+    pub fn in_check(&self) -> bool {
+        self.is_attacked(self.king_square(self.active), self.active.opposite())
+    }
+
+    /// Applies a move, leaving captured pieces out of the piece list
+    ///
+    /// The result is only in layout order if the move is not an exit
+    pub fn make_move(&self, mov: TbMove) -> TbPosition {
+        let mut position = TbPosition::empty(self.active.opposite());
+
+        for (slot, p) in self.pieces().iter().enumerate() {
+            if Some(slot) == mov.capture {
+                continue;
+            }
+
+            let mut p = *p;
+
+            if slot == mov.slot {
+                p.square = mov.to;
+                p.piece = mov.promotion.unwrap_or(p.piece);
+            }
+
+            position.push(p);
+        }
+
+        position
+    }
+
+    /// Generates all legal moves for the side to move
+    pub fn generate_moves(&self) -> Vec<TbMove> {
+        let occupied = self.occupied();
+        let own = self.color_occupied(self.active);
+        let mut moves = Vec::new();
+
+        for (slot, p) in self.pieces().iter().enumerate() {
+            if p.color != self.active {
+                continue;
+            }
+
+            let targets = match p.piece {
+                Piece::Pawn => {
+                    let forward = match p.color {
+                        Color::White => p.square - 8,
+                        Color::Black => p.square + 8,
+                    };
+
+                    let mut targets = Self::attacks(*p, occupied) & occupied & !own;
+
+                    if !occupied.bit_at(forward) {
+                        targets.set_bit_at(forward, true);
+
+                        // double moves from the starting rank
+                        let double = match p.color {
+                            Color::White if p.square >= 48 => Some(p.square - 16),
+                            Color::Black if p.square < 16 => Some(p.square + 16),
+                            _ => None,
+                        };
+
+                        if let Some(double) = double.filter(|sq| !occupied.bit_at(*sq)) {
+                            targets.set_bit_at(double, true);
+                        }
+                    }
+
+                    targets
+                }
+
+                _ => Self::attacks(*p, occupied) & !own,
+            };
+
+            for to in targets {
+                let capture = self.pieces().iter().position(|other| other.square == to);
+
+                // pawns reaching the back rank must promote
+                let promotions: &[Option<Piece>] =
+                    match p.piece == Piece::Pawn && !(8..56).contains(&to) {
+                        true => &PROMOTIONS,
+                        false => &[None],
+                    };
+
+                for promotion in promotions.iter().copied() {
+                    let mov = TbMove {
+                        slot,
+                        to,
+                        promotion,
+                        capture,
+                    };
+
+                    // only keep moves that don't leave the king in check
+                    let after = self.make_move(mov);
+                    if !after.is_attacked(after.king_square(self.active), after.active) {
+                        moves.push(mov);
+                    }
+                }
+            }
+        }
+
+        moves
+    }
+
+    /// Generates the indices of all positions that could have reached this one with a non-capturing, non-promoting move
+    ///
+    /// Predecessors are not checked for legality, so the caller must skip illegal ones
+    pub fn predecessors(&self) -> Vec<usize> {
+        let occupied = self.occupied();
+        let moved = self.active.opposite();
+        let mut indices = Vec::new();
+
+        for (slot, p) in self.pieces().iter().enumerate() {
+            if p.color != moved {
+                continue;
+            }
+
+            let origins = match p.piece {
+                Piece::Pawn => {
+                    let mut origins = Bitboard::EMPTY;
+
+                    let (single, double, start_rank) = match p.color {
+                        Color::White => (p.square + 8, p.square + 16, 48..56),
+                        Color::Black => {
+                            (p.square.wrapping_sub(8), p.square.wrapping_sub(16), 8..16)
+                        }
+                    };
+
+                    // a pawn can't have come from the back rank
+                    if (8..56).contains(&single) && !occupied.bit_at(single) {
+                        origins.set_bit_at(single, true);
+
+                        if start_rank.contains(&double) && !occupied.bit_at(double) {
+                            origins.set_bit_at(double, true);
+                        }
+                    }
+
+                    origins
+                }
+
+                // all other pieces move symmetrically, so they could have come from any square they attack
+                _ => Self::attacks(*p, occupied) & !occupied,
+            };
+
+            for from in origins {
+                let mut previous = *self;
+                previous.pieces[slot].square = from;
+                previous.active = moved;
+
+                indices.push(previous.index());
+            }
+        }
+
+        indices
+    }
+}
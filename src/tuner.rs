@@ -0,0 +1,278 @@
+use crate::{
+    board::Board,
+    core::Color,
+    search::{evaluate, EvalParams, EvalTables, Score},
+};
+use regex::Regex;
+use std::{fs, io, thread, time::Instant};
+
+/// Matches a line of the dataset, made up of a FEN string followed by the result of the game, in any of the forms
+/// `[1.0]`, `1-0`, or `c9 "1-0";` (as used by EPD files)
+const SAMPLE_REGEX: &str = r#"^(.+?)\s+(?:c9\s+)?"?(1-0|0-1|1/2-1/2|\[[0-9.]+\])"?;?$"#;
+
+/// Amount of decimal places the scaling constant is fitted to
+const K_PRECISION: i32 = 4;
+
+/// Position from a played game, labeled with the result of that game
+struct Sample {
+    board: Board,
+    result: f64, // 1 for a white win, 0.5 for a draw and 0 for a black win
+}
+
+/// Tunes the evaluation weights to predict the results of a dataset of labeled positions, using Texel's method
+///
+/// Evaluations are mapped to an expected result with a sigmoid scaled by a constant K, which is fitted to the dataset
+/// first. Each weight is then moved up or down by one at a time, keeping any change that lowers the mean squared error
+/// between the expected and actual results, until no weight can be improved further.
+///
+/// More info here: https://www.chessprogramming.org/Texel%27s_Tuning_Method
+pub struct Tuner {
+    samples: Vec<Sample>,
+    params: EvalParams,
+    k: f64,
+}
+
+impl Tuner {
+    /// Loads a dataset with one labeled position per line, skipping (and reporting) any invalid lines
+    pub fn load(path: &str) -> io::Result<Tuner> {
+        let regex = Regex::new(SAMPLE_REGEX).unwrap();
+        let mut samples = Vec::new();
+
+        for line in fs::read_to_string(path)?.lines() {
+            match Self::parse_line(&regex, line.trim()) {
+                Some(sample) => samples.push(sample),
+                None => println!("Skipping invalid tuning sample: {}", line),
+            }
+        }
+
+        Ok(Tuner {
+            samples,
+            params: EvalParams::default(),
+            k: 1.0,
+        })
+    }
+
+    /// Parses a single line of the dataset, FEN strings without move counters (as in EPD files) are accepted
+    fn parse_line(regex: &Regex, line: &str) -> Option<Sample> {
+        let captures = regex.captures(line)?;
+
+        let mut fen = captures[1].split_whitespace().collect::<Vec<_>>();
+        if fen.len() == 4 {
+            fen.extend(["0", "1"]);
+        }
+
+        let result = match &captures[2] {
+            "1-0" => 1.0,
+            "0-1" => 0.0,
+            "1/2-1/2" => 0.5,
+            result => result.trim_matches(['[', ']']).parse().ok()?,
+        };
+
+        Some(Sample {
+            board: Board::try_new(&fen.join(" "))?,
+            result,
+        })
+    }
+
+    /// Amount of positions in the dataset
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Evaluates every position in the dataset from white's point of view, split between threads
+    fn evaluate_all(&self, params: &EvalParams) -> Vec<Score> {
+        let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+        let chunk_size = self.samples.len().div_ceil(threads).max(1);
+
+        thread::scope(|scope| {
+            let handles = self
+                .samples
+                .chunks(chunk_size)
+                .map(|samples| {
+                    scope.spawn(move || {
+                        // cached entries would go stale as soon as the weights change
+                        let mut tables = EvalTables::uncached();
+
+                        samples
+                            .iter()
+                            .map(|sample| {
+                                let score = evaluate(&sample.board, params, &mut tables);
+
+                                match sample.board.active_color() {
+                                    Color::White => score,
+                                    Color::Black => -score,
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    }
+
+    /// Mean squared error between the results of the dataset and the results expected from the given evaluations
+    fn error(&self, scores: &[Score], k: f64) -> f64 {
+        let total: f64 = self
+            .samples
+            .iter()
+            .zip(scores)
+            .map(|(sample, score)| (sample.result - sigmoid(*score, k)).powi(2))
+            .sum();
+
+        total / self.samples.len() as f64
+    }
+
+    /// Finds the scaling constant that best maps the current evaluations to the results of the dataset
+    pub fn fit_k(&mut self) -> f64 {
+        let params = self.params.clone();
+        let scores = self.evaluate_all(&params);
+
+        let mut best = (self.k, self.error(&scores, self.k));
+        let mut step = 1.0;
+
+        // narrow down on the best value one decimal place at a time
+        for _ in 0..=K_PRECISION {
+            let start = f64::max(0.0, best.0 - 10.0 * step);
+
+            for i in 0..=20 {
+                let k = start + i as f64 * step;
+                let error = self.error(&scores, k);
+
+                if error < best.1 {
+                    best = (k, error);
+                }
+            }
+
+            step /= 10.0;
+        }
+
+        self.k = best.0;
+        self.k
+    }
+
+    /// Mean squared error of the dataset using the given weights
+    fn params_error(&self, params: &EvalParams) -> f64 {
+        let scores = self.evaluate_all(params);
+        self.error(&scores, self.k)
+    }
+
+    /// Runs local search over every weight until none can be improved or the iteration limit is reached
+    ///
+    /// The weights are written to the output file after every iteration, so progress isn't lost if tuning is stopped
+    pub fn tune(&mut self, max_iterations: usize, output: &str) -> io::Result<()> {
+        let mut params = self.params.clone();
+        let mut best_error = self.params_error(&params);
+        let weights = params.values_mut().len();
+
+        println!("Initial error: {:.6} (K = {})", best_error, self.k);
+
+        for iteration in 1..=max_iterations {
+            let start = Instant::now();
+            let mut improved = 0;
+
+            for index in 0..weights {
+                // try a step up first, then a step down, falling back to the original value
+                for change in [1, -2] {
+                    *params.values_mut()[index] += change;
+                    let error = self.params_error(&params);
+
+                    if error < best_error {
+                        best_error = error;
+                        improved += 1;
+                        break;
+                    }
+
+                    if change == -2 {
+                        *params.values_mut()[index] += 1;
+                    }
+                }
+            }
+
+            self.params = params.clone();
+            fs::write(output, self.params.to_string())?;
+
+            println!(
+                "Iteration {}: error {:.6}, {} weights changed in {:.1}s",
+                iteration,
+                best_error,
+                improved,
+                start.elapsed().as_secs_f32()
+            );
+
+            if improved == 0 {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Maps an evaluation to an expected result between 0 (black wins) and 1 (white wins)
+fn sigmoid(score: Score, k: f64) -> f64 {
+    1.0 / (1.0 + f64::powf(10.0, -k * score as f64 / 400.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_samples() {
+        let regex = Regex::new(SAMPLE_REGEX).unwrap();
+        let parse = |line: &str| Tuner::parse_line(&regex, line).map(|sample| sample.result);
+
+        assert_eq!(parse("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 [1.0]"), Some(1.0));
+        assert_eq!(parse("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1 0-1"), Some(0.0));
+        assert_eq!(
+            parse(r#"4k3/8/8/8/8/8/4P3/4K3 w - - c9 "1/2-1/2";"#),
+            Some(0.5)
+        );
+
+        // missing results and invalid positions are skipped
+        assert_eq!(parse("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"), None);
+        assert_eq!(parse("8/8/8/8/8/8/4P3/4K3 w - - 0 1 [0.5]"), None);
+    }
+
+    #[test]
+    fn test_tuning_lowers_error() {
+        let id = std::process::id();
+        let path = std::env::temp_dir().join(format!("otter_tuner_test_{}.txt", id));
+        let output = std::env::temp_dir().join(format!("otter_tuner_test_output_{}.txt", id));
+        let (path, output) = (path.to_str().unwrap(), output.to_str().unwrap());
+
+        // white is up material in every decisive game, but doesn't always convert it
+        let dataset = [
+            "4k3/8/8/8/8/8/3PP3/4K3 w - - 0 1 [1.0]",
+            "4k3/8/8/8/8/8/3PP3/4K3 b - - 0 1 [0.5]",
+            "4k3/8/8/8/8/8/2PPP3/4K3 b - - 0 1 [1.0]",
+            "r3k3/8/8/8/8/8/8/R3K2N w - - 0 1 [1.0]",
+            "r3k3/8/8/8/8/8/8/R3K2N b - - 0 1 [0.5]",
+            "4k3/4p3/8/8/8/8/4P3/4K3 w - - 0 1 [0.5]",
+        ];
+        fs::write(path, dataset.join("\n")).unwrap();
+
+        let mut tuner = Tuner::load(path).unwrap();
+        assert_eq!(tuner.len(), dataset.len());
+
+        let k = tuner.fit_k();
+        let params = EvalParams::default();
+        let initial_error = tuner.params_error(&params);
+
+        tuner.tune(1, output).unwrap();
+        let tuned = tuner.params.clone();
+        assert!(tuner.params_error(&tuned) < initial_error);
+        assert_eq!(tuner.k, k);
+
+        // the output file holds every tuned weight
+        assert_eq!(fs::read_to_string(output).unwrap(), tuned.to_string());
+
+        fs::remove_file(path).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+}